@@ -0,0 +1,500 @@
+//! Signing in with a Microsoft account to get a Minecraft access token for the built-in launcher.
+//!
+//! This uses the device code flow, so the user signs in using their browser. Only the Microsoft refresh token is stored, in the platform's credential store, and the other tokens are requested anew for each launch.
+
+use {
+    std::time::Duration,
+    reqwest::StatusCode,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    tokio::time::{
+        Instant,
+        sleep,
+    },
+    uuid::Uuid,
+    wheel::{
+        fs,
+        traits::{
+            IoResultExt as _,
+            IsNetworkError,
+            ReqwestResponseExt as _,
+        },
+    },
+    crate::config,
+};
+
+const SCOPE: &str = "XboxLive.signin offline_access";
+const KEYRING_SERVICE: &str = "Wurstmineberg";
+const KEYRING_USER: &str = "microsoft-refresh-token";
+/// The name of the file in the data folder where the [`OfflineProfile`] is kept.
+const OFFLINE_PROFILE_FILE: &str = "offline-profile.json";
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Config(#[from] config::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Keyring(#[from] keyring::Error),
+    #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("the sign-in code has expired, please try again")]
+    CodeExpired,
+    #[error("sign-in was declined")]
+    Declined,
+    #[error("Microsoft sign-in failed: {0}")]
+    Microsoft(String),
+    #[error("no client ID for Microsoft sign-in, please configure `signIn.clientId`")]
+    NoClientId,
+    #[error("this Microsoft account doesn't own Minecraft: Java Edition")]
+    NoMinecraftProfile,
+    #[error("this Microsoft account has no Xbox profile, please sign in at https://www.minecraft.net/ once to create one")]
+    NoXboxAccount,
+    #[error("failed to reach the Minecraft sign-in servers, and there's no previous sign-in to play offline with: {0}")]
+    Offline(#[source] Box<Error>),
+    #[error("the stored sign-in has expired, please sign in again")]
+    SignInExpired,
+    #[error("Xbox Live sign-in failed with error code {0}")]
+    Xsts(u64),
+    #[error("Xbox Live sent a response without user info")]
+    XboxLiveUserInfo,
+}
+
+impl IsNetworkError for Error {
+    fn is_network_error(&self) -> bool {
+        match self {
+            Self::Reqwest(e) => e.is_network_error(),
+            Self::Wheel(e) => e.is_network_error(),
+            Self::Offline(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A Minecraft account and an access token for it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Account {
+    /// The player name.
+    pub(crate) name: String,
+    pub(crate) uuid: Uuid,
+    pub(crate) access_token: String,
+    /// The Xbox user ID, passed to the game for telemetry.
+    pub(crate) xuid: Option<String>,
+}
+
+/// The profile of the most recently signed-in account, kept so the game can still be launched (e.g. into singleplayer) while the sign-in servers are unreachable.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OfflineProfile {
+    name: String,
+    uuid: Uuid,
+    xuid: Option<String>,
+}
+
+/// The code the user has to enter on Microsoft's website to sign in.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DeviceCode {
+    pub(crate) user_code: String,
+    pub(crate) verification_uri: String,
+    device_code: String,
+    /// In seconds.
+    expires_in: u64,
+    /// The minimum number of seconds between checks whether the user has signed in.
+    interval: u64,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftTokens {
+    access_token: String,
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl From<OAuthError> for Error {
+    fn from(OAuthError { error, error_description }: OAuthError) -> Self {
+        Self::Microsoft(error_description.unwrap_or(error))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxLiveRequest<'a> {
+    properties: XboxLiveProperties,
+    relying_party: &'a str,
+    token_type: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxLiveProperties {
+    auth_method: &'static str,
+    site_name: &'static str,
+    rps_ticket: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct XstsRequest<'a> {
+    properties: XstsProperties<'a>,
+    relying_party: &'a str,
+    token_type: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct XstsProperties<'a> {
+    sandbox_id: &'a str,
+    user_tokens: [&'a str; 1],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxLiveResponse {
+    token: String,
+    display_claims: DisplayClaims,
+}
+
+#[derive(Deserialize)]
+struct DisplayClaims {
+    xui: Vec<UserInfo>,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    /// The user hash.
+    uhs: String,
+    /// The Xbox user ID, only included in XSTS responses.
+    xid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XstsError {
+    x_err: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MinecraftLoginRequest {
+    identity_token: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftLoginResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftProfile {
+    id: Uuid,
+    name: String,
+}
+
+/// Requests tokens from Microsoft's token endpoint. OAuth errors like a pending device code sign-in are returned as the inner error.
+async fn token_request(http_client: &reqwest::Client, config: &config::SignIn, form: &[(&str, &str)]) -> Result<Result<MicrosoftTokens, OAuthError>, Error> {
+    let response = http_client.post(&config.token_url)
+        .form(form)
+        .send().await?;
+    Ok(if response.status() == StatusCode::BAD_REQUEST {
+        Err(response.json_with_text_in_error().await?)
+    } else {
+        Ok(response.detailed_error_for_status().await?.json_with_text_in_error().await?)
+    })
+}
+
+/// Exchanges a Microsoft access token for a Minecraft access token via Xbox Live and looks up the Minecraft profile.
+async fn minecraft_login(http_client: &reqwest::Client, config: &config::SignIn, microsoft_token: &str) -> Result<Account, Error> {
+    let xbox_live = http_client.post(&config.xbox_live_url)
+        .json(&XboxLiveRequest {
+            properties: XboxLiveProperties {
+                auth_method: "RPS",
+                site_name: "user.auth.xboxlive.com",
+                rps_ticket: format!("d={microsoft_token}"),
+            },
+            relying_party: "http://auth.xboxlive.com",
+            token_type: "JWT",
+        })
+        .send().await?
+        .detailed_error_for_status().await?
+        .json_with_text_in_error::<XboxLiveResponse>().await?;
+    let response = http_client.post(&config.xsts_url)
+        .json(&XstsRequest {
+            properties: XstsProperties {
+                sandbox_id: "RETAIL",
+                user_tokens: [xbox_live.token.as_str()],
+            },
+            relying_party: "rp://api.minecraftservices.com/",
+            token_type: "JWT",
+        })
+        .send().await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Err(match response.json_with_text_in_error::<XstsError>().await?.x_err {
+            2148916233 => Error::NoXboxAccount,
+            code => Error::Xsts(code),
+        })
+    }
+    let xsts = response.detailed_error_for_status().await?.json_with_text_in_error::<XboxLiveResponse>().await?;
+    let user_info = xsts.display_claims.xui.into_iter().next().ok_or(Error::XboxLiveUserInfo)?;
+    let login = http_client.post(&config.minecraft_login_url)
+        .json(&MinecraftLoginRequest { identity_token: format!("XBL3.0 x={};{}", user_info.uhs, xsts.token) })
+        .send().await?
+        .detailed_error_for_status().await?
+        .json_with_text_in_error::<MinecraftLoginResponse>().await?;
+    let response = http_client.get(&config.minecraft_profile_url)
+        .bearer_auth(&login.access_token)
+        .send().await?;
+    if response.status() == StatusCode::NOT_FOUND { return Err(Error::NoMinecraftProfile) }
+    let profile = response.detailed_error_for_status().await?.json_with_text_in_error::<MinecraftProfile>().await?;
+    Ok(Account {
+        name: profile.name,
+        uuid: profile.id,
+        access_token: login.access_token,
+        xuid: user_info.xid,
+    })
+}
+
+/// Signs in using the device code flow and stores the sign-in for future launches.
+///
+/// `on_code` is called with the code the user has to enter, and the returned future resolves once they have signed in.
+pub(crate) async fn sign_in(http_client: &reqwest::Client, config: &config::SignIn, on_code: impl FnOnce(DeviceCode)) -> Result<Account, Error> {
+    let client_id = config.client_id.as_deref().ok_or(Error::NoClientId)?;
+    let code = http_client.post(&config.device_code_url)
+        .form(&[("client_id", client_id), ("scope", SCOPE)])
+        .send().await?
+        .detailed_error_for_status().await?
+        .json_with_text_in_error::<DeviceCode>().await?;
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval.max(1));
+    let device_code = code.device_code.clone();
+    on_code(code);
+    let tokens = loop {
+        sleep(interval).await;
+        if Instant::now() >= deadline { return Err(Error::CodeExpired) }
+        match token_request(http_client, config, &[("grant_type", "urn:ietf:params:oauth:grant-type:device_code"), ("client_id", client_id), ("device_code", &device_code)]).await? {
+            Ok(tokens) => break tokens,
+            Err(e) => match &*e.error {
+                "authorization_pending" => {}
+                "slow_down" => interval += Duration::from_secs(5),
+                "expired_token" => return Err(Error::CodeExpired),
+                "authorization_declined" | "access_denied" => return Err(Error::Declined),
+                _ => return Err(e.into()),
+            },
+        }
+    };
+    let account = minecraft_login(http_client, config, &tokens.access_token).await?;
+    store_refresh_token(tokens.refresh_token).await?;
+    save_offline_profile(&account).await?;
+    log::info!("signed in as {}", account.name);
+    Ok(account)
+}
+
+/// Removes the stored sign-in, if any.
+pub(crate) async fn sign_out() -> Result<(), Error> {
+    tokio::task::spawn_blocking(|| match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    }).await??;
+    let path = config::data_dir()?.join(OFFLINE_PROFILE_FILE);
+    if fs::exists(&path).await? {
+        tokio::fs::remove_file(&path).await.at(path)?;
+    }
+    log::info!("signed out");
+    Ok(())
+}
+
+async fn store_refresh_token(refresh_token: String) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?.set_password(&refresh_token)).await??;
+    Ok(())
+}
+
+async fn stored_refresh_token() -> Result<Option<String>, Error> {
+    Ok(tokio::task::spawn_blocking(|| match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?.get_password() {
+        Ok(refresh_token) => Ok(Some(refresh_token)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }).await??)
+}
+
+async fn save_offline_profile(account: &Account) -> Result<(), Error> {
+    let mut buf = serde_json::to_string_pretty(&OfflineProfile { name: account.name.clone(), uuid: account.uuid, xuid: account.xuid.clone() })?;
+    buf.push('\n');
+    let dir = config::data_dir()?;
+    fs::create_dir_all(&dir).await?;
+    fs::write(dir.join(OFFLINE_PROFILE_FILE), buf).await?;
+    Ok(())
+}
+
+/// Exchanges a refresh token for a Minecraft access token. Returns the account along with the new refresh token, since Microsoft may rotate it.
+async fn refresh(http_client: &reqwest::Client, config: &config::SignIn, refresh_token: &str) -> Result<(Account, String), Error> {
+    let client_id = config.client_id.as_deref().ok_or(Error::NoClientId)?;
+    let tokens = match token_request(http_client, config, &[("grant_type", "refresh_token"), ("client_id", client_id), ("refresh_token", refresh_token), ("scope", SCOPE)]).await? {
+        Ok(tokens) => tokens,
+        Err(e) if e.error == "invalid_grant" => return Err(Error::SignInExpired),
+        Err(e) => return Err(e.into()),
+    };
+    let account = minecraft_login(http_client, config, &tokens.access_token).await?;
+    Ok((account, tokens.refresh_token))
+}
+
+/// The account to launch Minecraft with, with a fresh access token.
+///
+/// An `account.json` file in the data folder takes precedence over the account signed in via [`sign_in`]. Returns `None` if there is neither.
+///
+/// If the sign-in servers can't be reached, the most recently signed-in profile is returned with an invalid access token, which is enough to play singleplayer.
+pub(crate) async fn account(http_client: &reqwest::Client, config: &config::SignIn) -> Result<Option<Account>, Error> {
+    let path = config::data_dir()?.join("account.json");
+    if let Some(account) = fs::read_json(path).await.map(Some).missing_ok()? {
+        return Ok(Some(account))
+    }
+    let Some(refresh_token) = stored_refresh_token().await? else { return Ok(None) };
+    match refresh(http_client, config, &refresh_token).await {
+        Ok((account, refresh_token)) => {
+            store_refresh_token(refresh_token).await?;
+            save_offline_profile(&account).await?;
+            Ok(Some(account))
+        }
+        Err(e) if e.is_network_error() => {
+            let path = config::data_dir()?.join(OFFLINE_PROFILE_FILE);
+            let Some(profile) = fs::read_json::<OfflineProfile>(path).await.map(Some).missing_ok()? else { return Err(Error::Offline(Box::new(e))) };
+            log::warn!("failed to sign in to Minecraft, launching as {} for offline play: {e} ({e:?})", profile.name);
+            Ok(Some(Account {
+                name: profile.name,
+                uuid: profile.uuid,
+                access_token: format!("offline"),
+                xuid: profile.xuid,
+            }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        axum::{
+            Router,
+            http::{
+                StatusCode,
+                header,
+            },
+            response::IntoResponse,
+            routing::{
+                get,
+                post,
+            },
+        },
+        crate::test_util,
+        super::*,
+    };
+
+    const UUID: &str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+    fn json(status: StatusCode, body: &'static str) -> impl IntoResponse {
+        (status, [(header::CONTENT_TYPE, "application/json")], body)
+    }
+
+    /// Stand-ins for the Microsoft, Xbox Live, and Minecraft services endpoints. Requests are checked only as far as needed to tell the tests apart.
+    fn router(xsts_status: StatusCode, profile_status: StatusCode) -> Router {
+        Router::new()
+            .route("/token", post(|body: String| async move {
+                if body.contains("refresh_token=valid") {
+                    json(StatusCode::OK, r#"{"access_token": "microsoft-token", "refresh_token": "rotated", "token_type": "Bearer"}"#)
+                } else {
+                    json(StatusCode::BAD_REQUEST, r#"{"error": "invalid_grant", "error_description": "the refresh token has expired"}"#)
+                }
+            }))
+            .route("/xbox-live", post(|body: String| async move {
+                assert!(body.contains("d=microsoft-token"));
+                json(StatusCode::OK, r#"{"Token": "xbox-live-token", "DisplayClaims": {"xui": [{"uhs": "user-hash"}]}}"#)
+            }))
+            .route("/xsts", post(move |body: String| async move {
+                assert!(body.contains("xbox-live-token"));
+                if xsts_status == StatusCode::OK {
+                    json(StatusCode::OK, r#"{"Token": "xsts-token", "DisplayClaims": {"xui": [{"uhs": "user-hash", "xid": "2535400000000000"}]}}"#)
+                } else {
+                    json(xsts_status, r#"{"XErr": 2148916233, "Message": ""}"#)
+                }
+            }))
+            .route("/minecraft-login", post(|body: String| async move {
+                assert!(body.contains("XBL3.0 x=user-hash;xsts-token"));
+                json(StatusCode::OK, r#"{"access_token": "minecraft-token", "expires_in": 86400}"#)
+            }))
+            .route("/minecraft-profile", get(move || async move {
+                if profile_status == StatusCode::OK {
+                    json(StatusCode::OK, r#"{"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"}"#)
+                } else {
+                    json(profile_status, r#"{"error": "NOT_FOUND"}"#)
+                }
+            }))
+    }
+
+    fn config(base_url: &str) -> config::SignIn {
+        config::SignIn {
+            client_id: Some(format!("client-id")),
+            device_code_url: format!("{base_url}/device-code"),
+            token_url: format!("{base_url}/token"),
+            xbox_live_url: format!("{base_url}/xbox-live"),
+            xsts_url: format!("{base_url}/xsts"),
+            minecraft_login_url: format!("{base_url}/minecraft-login"),
+            minecraft_profile_url: format!("{base_url}/minecraft-profile"),
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_signs_in() {
+        let config = config(&test_util::serve(router(StatusCode::OK, StatusCode::OK)).await);
+        let (account, refresh_token) = refresh(&test_util::http_client(), &config, "valid").await.unwrap();
+        assert_eq!(account.name, "Notch");
+        assert_eq!(account.uuid, UUID.parse::<Uuid>().unwrap());
+        assert_eq!(account.access_token, "minecraft-token");
+        assert_eq!(account.xuid.as_deref(), Some("2535400000000000"));
+        assert_eq!(refresh_token, "rotated");
+    }
+
+    #[tokio::test]
+    async fn refresh_expired() {
+        let config = config(&test_util::serve(router(StatusCode::OK, StatusCode::OK)).await);
+        let result = refresh(&test_util::http_client(), &config, "expired").await;
+        assert!(matches!(result, Err(Error::SignInExpired)));
+    }
+
+    #[tokio::test]
+    async fn refresh_without_xbox_account() {
+        let config = config(&test_util::serve(router(StatusCode::UNAUTHORIZED, StatusCode::OK)).await);
+        let result = refresh(&test_util::http_client(), &config, "valid").await;
+        assert!(matches!(result, Err(Error::NoXboxAccount)));
+    }
+
+    #[tokio::test]
+    async fn refresh_without_minecraft() {
+        let config = config(&test_util::serve(router(StatusCode::OK, StatusCode::NOT_FOUND)).await);
+        let result = refresh(&test_util::http_client(), &config, "valid").await;
+        assert!(matches!(result, Err(Error::NoMinecraftProfile)));
+    }
+
+    #[tokio::test]
+    async fn refresh_offline() {
+        // a port that was free a moment ago, so connecting fails
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let config = config(&format!("http://{addr}"));
+        let result = refresh(&test_util::http_client(), &config, "valid").await;
+        assert!(result.is_err_and(|e| e.is_network_error()));
+    }
+
+    #[tokio::test]
+    async fn no_client_id() {
+        let config = config::SignIn {
+            client_id: None,
+            ..config("http://127.0.0.1:1")
+        };
+        let result = refresh(&test_util::http_client(), &config, "valid").await;
+        assert!(matches!(result, Err(Error::NoClientId)));
+    }
+}
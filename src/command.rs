@@ -0,0 +1,111 @@
+//! Running child processes while processing their output as it arrives.
+
+use {
+    std::process::{
+        Output,
+        Stdio,
+    },
+    tokio::{
+        io::{
+            AsyncBufReadExt as _,
+            BufReader,
+        },
+        process::{
+            Child,
+            Command,
+        },
+    },
+    wheel::traits::IoResultExt as _,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("command `{name}` exited with {}", .output.status)]
+    Exit {
+        name: &'static str,
+        output: Output,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Formats the program and arguments of the command like they would be typed into a shell, for logging.
+///
+/// The values of arguments that look like credentials are replaced with `***`.
+pub(crate) fn display(cmd: &Command) -> String {
+    let cmd = cmd.as_std();
+    let mut redact_next = false;
+    let mut words = vec![quote(&cmd.get_program().to_string_lossy())];
+    for arg in cmd.get_args() {
+        let arg = arg.to_string_lossy();
+        words.push(if redact_next { format!("***") } else { quote(&arg) });
+        // e.g. `--github-token` for ferium or `--accessToken` for Minecraft itself
+        redact_next = arg.to_ascii_lowercase().ends_with("token");
+    }
+    words.join(" ")
+}
+
+fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(char::is_whitespace) && !word.contains('"') {
+        word.to_owned()
+    } else {
+        format!("\"{}\"", word.replace('"', "\\\""))
+    }
+}
+
+/// Sets up the command's standard output and error to be read by [`wait`].
+pub(crate) fn pipe(cmd: &mut Command) -> &mut Command {
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd
+}
+
+/// Runs the command to completion, calling `on_line` with each line of standard output or error as soon as it's printed.
+///
+/// Like `wheel`'s `check`, this returns an error if the command exits unsuccessfully.
+pub(crate) async fn run(cmd: &mut Command, name: &'static str, on_line: impl FnMut(Stream, &str)) -> Result<Output, Error> {
+    let child = pipe(cmd).spawn().at_command(name)?;
+    wait(child, name, on_line).await
+}
+
+/// Waits for a child process spawned from a command that was set up using [`pipe`], calling `on_line` with each line of standard output or error as soon as it's printed.
+///
+/// Like `wheel`'s `check`, this returns an error if the command exits unsuccessfully.
+pub(crate) async fn wait(mut child: Child, name: &'static str, mut on_line: impl FnMut(Stream, &str)) -> Result<Output, Error> {
+    let mut stdout_lines = BufReader::new(child.stdout.take().expect("stdout was piped")).lines();
+    let mut stderr_lines = BufReader::new(child.stderr.take().expect("stderr was piped")).lines();
+    let mut stdout = Vec::default();
+    let mut stderr = Vec::default();
+    let mut stdout_done = false;
+    let mut stderr_done = false;
+    while !stdout_done || !stderr_done {
+        let (stream, line) = tokio::select! {
+            line = stdout_lines.next_line(), if !stdout_done => (Stream::Stdout, line.at_command(name)?),
+            line = stderr_lines.next_line(), if !stderr_done => (Stream::Stderr, line.at_command(name)?),
+        };
+        let (buf, done) = match stream {
+            Stream::Stdout => (&mut stdout, &mut stdout_done),
+            Stream::Stderr => (&mut stderr, &mut stderr_done),
+        };
+        if let Some(line) = line {
+            on_line(stream, &line);
+            buf.extend_from_slice(line.as_bytes());
+            buf.push(b'\n');
+        } else {
+            *done = true;
+        }
+    }
+    let status = child.wait().await.at_command(name)?;
+    let output = Output { status, stdout, stderr };
+    if status.success() {
+        Ok(output)
+    } else {
+        Err(Error::Exit { name, output })
+    }
+}
@@ -1,14 +1,25 @@
 use {
     std::{
-        collections::HashMap,
+        collections::{
+            BTreeMap,
+            HashMap,
+        },
         fs,
+        net::SocketAddr,
+        path::PathBuf,
     },
     directories::BaseDirs,
+    log::LevelFilter,
     serde::Deserialize,
+    smart_default::SmartDefault,
     tokio::process::Command,
     uuid::Uuid,
     wheel::traits::IoResultExt as _,
-    crate::Uid,
+    crate::{
+        Uid,
+        events,
+        loader::Loader,
+    },
 };
 
 fn make_true() -> bool { true }
@@ -16,15 +27,49 @@ fn make_true() -> bool { true }
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Config {
+    /// Run whenever something happens on one of the worlds, such as a player joining.
+    #[serde(default)]
+    pub(crate) hooks: Vec<Hook>,
     #[serde(default)]
     pub(crate) ignored_players: Vec<Uid>,
     #[serde(default = "make_true")]
     pub(crate) left_click_launch: bool,
+    /// The maximum level of messages written to the log file. Can be overridden using the `--log-level` command-line option.
+    pub(crate) log_level: Option<LevelFilter>,
+    #[serde(default)]
+    pub(crate) daemon: Daemon,
+    /// If specified, the Minecraft session is shown as Discord Rich Presence while a game launched by this app is running.
+    pub(crate) discord: Option<Discord>,
     #[serde(default)]
     pub(crate) ferium: Ferium,
     #[serde(default)]
+    pub(crate) java: Java,
+    /// Overrides the location of the `.minecraft` folder, which is otherwise searched in the standard locations.
+    pub(crate) minecraft_dir: Option<PathBuf>,
+    /// Maps Wurstmineberg world names to the mod loaders to launch them with.
+    #[serde(default)]
+    pub(crate) mod_loaders: HashMap<String, ModLoader>,
+    /// If specified, the server state is published to this MQTT broker.
+    pub(crate) mqtt: Option<Mqtt>,
+    /// Maps Wurstmineberg world names to Modrinth modpacks.
+    #[serde(default)]
+    pub(crate) mrpack: HashMap<String, Mrpack>,
+    /// If specified, Minecraft is installed and launched by this app itself rather than an external launcher.
+    pub(crate) native_launcher: Option<NativeLauncher>,
+    /// Maps Wurstmineberg world names to packwiz modpacks.
+    #[serde(default)]
+    pub(crate) packwiz: HashMap<String, Packwiz>,
+    #[serde(default)]
     pub(crate) portablemc: PortableMc,
     pub(crate) prism_instance: Option<String>,
+    /// Maps Wurstmineberg world names to the Fabric mods required to join, as mod IDs mapped to versions.
+    #[serde(default)]
+    pub(crate) required_mods: HashMap<String, BTreeMap<String, String>>,
+    /// Maps Wurstmineberg world names to the server addresses used to join them.
+    #[serde(default)]
+    pub(crate) server_addresses: HashMap<String, String>,
+    #[serde(default)]
+    pub(crate) sign_in: SignIn,
     #[serde(default)]
     pub(crate) show_if_empty: bool,
     #[serde(default)]
@@ -40,32 +85,74 @@ pub(crate) enum Error {
     #[error(transparent)] Wheel(#[from] wheel::Error),
     #[error("failed to find user folder")]
     BaseDirs,
+    #[error("the JVM argument {0:?} in `java.args` contains whitespace, which isn't supported since the arguments are passed to launchers as a single space-separated list")]
+    JvmArgWhitespace(String),
+}
+
+/// The folder where the config file, logs, and other data of this app are stored.
+pub(crate) fn data_dir() -> Result<PathBuf, Error> {
+    Ok(BaseDirs::new().ok_or(Error::BaseDirs)?.data_dir().join("Wurstmineberg"))
+}
+
+/// Opens the config file in the default editor, creating an empty one first if necessary.
+pub(crate) fn open_file() -> Result<(), Error> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir).at(&dir)?;
+    let path = dir.join("config.json");
+    if !path.exists() {
+        fs::write(&path, "{}\n").at(&path)?;
+    }
+    open::that(path)?;
+    Ok(())
 }
 
 impl Config {
     pub(crate) fn blocking_load() -> Result<Self, Error> {
-        let path = BaseDirs::new().ok_or(Error::BaseDirs)?.data_dir().join("Wurstmineberg").join("config.json");
-        Ok(if path.exists() {
-            serde_json::from_str(&fs::read_to_string(path)?)?
+        let path = data_dir()?.join("config.json");
+        let config = if path.exists() {
+            serde_json::from_str::<Self>(&fs::read_to_string(path)?)?
         } else {
             Self::default()
-        })
+        };
+        config.validate()
     }
 
     pub(crate) async fn load() -> Result<Self, Error> {
-        let path = BaseDirs::new().ok_or(Error::BaseDirs)?.data_dir().join("Wurstmineberg").join("config.json");
-        Ok(wheel::fs::read_json(path).await.missing_ok()?)
+        let path = data_dir()?.join("config.json");
+        wheel::fs::read_json::<Self>(path).await.missing_ok()?.validate()
+    }
+
+    /// Rejects settings that are syntactically valid but can't be used.
+    fn validate(self) -> Result<Self, Error> {
+        if let Some(arg) = self.java.args.iter().find(|arg| arg.contains(char::is_whitespace)) {
+            return Err(Error::JvmArgWhitespace(arg.clone()))
+        }
+        Ok(self)
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            hooks: Vec::default(),
             ignored_players: Vec::default(),
             left_click_launch: true,
+            log_level: None,
+            daemon: Daemon::default(),
+            discord: None,
             ferium: Ferium::default(),
+            java: Java::default(),
+            minecraft_dir: None,
+            mod_loaders: HashMap::default(),
+            mqtt: None,
+            mrpack: HashMap::default(),
+            native_launcher: None,
+            packwiz: HashMap::default(),
             portablemc: PortableMc::default(),
             prism_instance: None,
+            required_mods: HashMap::default(),
+            server_addresses: HashMap::default(),
+            sign_in: SignIn::default(),
             show_if_empty: false,
             show_if_offline: false,
             version_match: HashMap::default(),
@@ -73,6 +160,25 @@ impl Default for Config {
     }
 }
 
+/// Configuration for the `daemon` subcommand.
+#[derive(Debug, Clone, Deserialize, SmartDefault)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Daemon {
+    /// Where the status API is served. Defaults to port 24464 on localhost.
+    #[default(SocketAddr::from(([127, 0, 0, 1], 24464)))]
+    pub(crate) address: SocketAddr,
+}
+
+/// Configuration for Discord Rich Presence.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Discord {
+    /// The ID of the Discord application the activity is shown as.
+    pub(crate) client_id: String,
+    /// Overrides the location of the Discord client's IPC socket (a named pipe on Windows), e.g. for testing. By default, the standard locations are searched.
+    pub(crate) ipc_path: Option<PathBuf>,
+}
+
 /// Configuration for <https://github.com/gorilla-devs/ferium>
 #[derive(Debug, Default, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -82,6 +188,8 @@ pub(crate) struct Ferium {
     pub(crate) profiles: HashMap<String, String>,
     pub(crate) version_override: Option<String>,
     pub(crate) github_token: Option<String>,
+    /// Overrides the location of ferium's config file.
+    pub(crate) config_file: Option<PathBuf>,
 }
 
 impl Ferium {
@@ -91,10 +199,151 @@ impl Ferium {
             cmd.arg("--github-token");
             cmd.arg(github_token);
         }
+        if let Some(ref config_file) = self.config_file {
+            cmd.arg("--config-file");
+            cmd.arg(config_file);
+        }
         cmd
     }
 }
 
+/// The Java runtime and JVM arguments to launch Minecraft with.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Java {
+    /// The Java executable to use. Checked against the Java version required by the target Minecraft version.
+    pub(crate) path: Option<PathBuf>,
+    /// If `path` is not set, look for an installed Java matching the version required by the target Minecraft version.
+    #[serde(default)]
+    pub(crate) auto_select: bool,
+    /// Initial heap size in MiB.
+    pub(crate) min_memory: Option<u32>,
+    /// Maximum heap size in MiB.
+    pub(crate) max_memory: Option<u32>,
+    /// Additional JVM arguments.
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModLoader {
+    pub(crate) loader: Loader,
+    /// Defaults to the version from the world's modpack, if any, otherwise the latest version or the one already installed.
+    pub(crate) version: Option<String>,
+}
+
+/// A Modrinth modpack (`.mrpack` file) to install before launching.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Mrpack {
+    /// URL or local path of the `.mrpack` file.
+    pub(crate) pack: String,
+    /// The game folder to install the pack into. Defaults to the game folder of the Prism Launcher instance configured via `prismInstance`.
+    pub(crate) instance_dir: Option<PathBuf>,
+}
+
+fn default_hook_timeout() -> u64 { 10 }
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Hook {
+    /// The kinds of events this hook runs for. If empty, it runs for all events.
+    #[serde(default)]
+    pub(crate) events: Vec<events::Kind>,
+    /// In seconds.
+    #[serde(default = "default_hook_timeout")]
+    pub(crate) timeout: u64,
+    #[serde(flatten)]
+    pub(crate) action: HookAction,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum HookAction {
+    /// A command line, with the event data in environment variables.
+    Command {
+        command: Vec<String>,
+    },
+    /// A URL the event is POSTed to as JSON.
+    Webhook {
+        url: String,
+    },
+}
+
+fn default_mqtt_port() -> u16 { 1883 }
+fn default_mqtt_topic_prefix() -> String { format!("wurstmineberg") }
+fn default_mqtt_discovery_prefix() -> Option<String> { Some(format!("homeassistant")) }
+
+/// Configuration for publishing the server state via MQTT.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Mqtt {
+    pub(crate) host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub(crate) port: u16,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub(crate) topic_prefix: String,
+    /// The topic prefix for Home Assistant discovery messages, or `None` to not send any.
+    #[serde(default = "default_mqtt_discovery_prefix")]
+    pub(crate) discovery_prefix: Option<String>,
+}
+
+/// Configuration for the built-in launcher.
+///
+/// The metadata servers can be replaced with URLs or local paths of folders laid out the same way, e.g. for testing.
+#[derive(Debug, Clone, Deserialize, SmartDefault)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct NativeLauncher {
+    /// The game folder. Defaults to the world's modpack folder, if any, otherwise the `.minecraft` folder.
+    pub(crate) game_dir: Option<PathBuf>,
+    /// Where downloaded game files are kept. Defaults to a `cache` folder next to the config file.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// URL or local path of Mojang's version manifest.
+    #[default = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json"]
+    pub(crate) version_manifest: String,
+    /// URL or local path of the folder game assets are downloaded from.
+    #[default = "https://resources.download.minecraft.net/"]
+    pub(crate) resources: String,
+    /// URL or local path of the Fabric metadata server.
+    #[default = "https://meta.fabricmc.net/"]
+    pub(crate) fabric_meta: String,
+}
+
+/// A <https://packwiz.infra.link/> modpack to install before launching.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Packwiz {
+    /// URL or local path of the pack's `pack.toml`.
+    pub(crate) pack: String,
+    /// The game folder to install the pack into.
+    pub(crate) instance_dir: PathBuf,
+}
+
+/// Configuration for signing in with a Microsoft account for the built-in launcher.
+///
+/// The endpoints can be replaced, e.g. for testing.
+#[derive(Debug, Clone, Deserialize, SmartDefault)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct SignIn {
+    /// The ID of an Azure application with permission to use the Minecraft services API.
+    pub(crate) client_id: Option<String>,
+    #[default = "https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode"]
+    pub(crate) device_code_url: String,
+    #[default = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token"]
+    pub(crate) token_url: String,
+    #[default = "https://user.auth.xboxlive.com/user/authenticate"]
+    pub(crate) xbox_live_url: String,
+    #[default = "https://xsts.auth.xboxlive.com/xsts/authorize"]
+    pub(crate) xsts_url: String,
+    #[default = "https://api.minecraftservices.com/authentication/login_with_xbox"]
+    pub(crate) minecraft_login_url: String,
+    #[default = "https://api.minecraftservices.com/minecraft/profile"]
+    pub(crate) minecraft_profile_url: String,
+}
+
 /// Configuration for <https://github.com/mindstorm38/portablemc>
 #[derive(Debug, Default, Clone, Deserialize)]
 pub(crate) struct PortableMc {
@@ -0,0 +1,208 @@
+//! The `daemon` subcommand, which keeps the server state up to date without any GUI and serves it to other local programs.
+//!
+//! The status API has two endpoints:
+//!
+//! * `GET /status` returns the current state as JSON. With the `after` query parameter set to a `generation` from a previous response, the request waits (for up to a minute) until there is a newer state. If `after` is ahead of the current generation, for example because the daemon was restarted, the current state is returned right away.
+//! * `GET /events` is a [server-sent event](https://html.spec.whatwg.org/multipage/server-sent-events.html) stream, starting with the current state and sending the new state whenever it's updated.
+
+use {
+    std::{
+        collections::BTreeMap,
+        time::Duration,
+    },
+    axum::{
+        Json,
+        Router,
+        extract::{
+            Query,
+            State,
+        },
+        response::sse::{
+            Event,
+            KeepAlive,
+            Sse,
+        },
+        routing::get,
+    },
+    futures::stream::{
+        Stream,
+        StreamExt as _,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    tokio::{
+        io,
+        net::TcpListener,
+        sync::watch,
+        time::timeout,
+    },
+    tokio_stream::wrappers::WatchStream,
+    crate::{
+        config::Config,
+        loader::Loader,
+        tray::SharedState,
+    },
+};
+
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Maintain(#[from] crate::Error),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    /// Incremented with each update, for use with the `after` query parameter.
+    generation: u64,
+    #[serde(flatten)]
+    status: Status,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum Status {
+    Loading,
+    Error {
+        message: String,
+    },
+    Ready {
+        worlds: BTreeMap<String, World>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct World {
+    running: bool,
+    version: Option<String>,
+    loader: Option<Loader>,
+    /// The address to join the world at, including overrides from the `serverAddresses` config entry.
+    address: Option<String>,
+    /// Online players, excluding those in the `ignoredPlayers` config entry.
+    players: Vec<Player>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Player {
+    uid: String,
+    /// The player's display name, if they have one.
+    name: Option<String>,
+}
+
+impl Snapshot {
+    fn new(generation: u64, config: &Config, state: &SharedState) -> Self {
+        Self {
+            status: match *state {
+                Some(Ok((ref people, ref statuses))) => Status::Ready {
+                    worlds: statuses.iter().map(|(world_name, status)| (world_name.clone(), World {
+                        running: status.running,
+                        version: status.version.clone(),
+                        loader: status.loader,
+                        address: crate::server_address(config, world_name, Some(status)),
+                        players: status.list.iter().map(|uid| Player {
+                            uid: uid.to_string(),
+                            name: people.get(uid).and_then(|person| person.name.clone()),
+                        }).collect(),
+                    })).collect(),
+                },
+                Some(Err(ref e)) => Status::Error { message: e.to_string() },
+                None => Status::Loading,
+            },
+            generation,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StatusQuery {
+    after: Option<u64>,
+}
+
+async fn status(State(mut rx): State<watch::Receiver<Snapshot>>, Query(StatusQuery { after }): Query<StatusQuery>) -> Json<Snapshot> {
+    let current = rx.borrow().generation;
+    if let Some(after) = after && after <= current {
+        // on timeout, respond with the unchanged state so clients can tell the daemon is still alive
+        let _ = timeout(LONG_POLL_TIMEOUT, rx.wait_for(|snapshot| snapshot.generation > after)).await;
+    }
+    Json(rx.borrow().clone())
+}
+
+async fn events(State(rx): State<watch::Receiver<Snapshot>>) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    Sse::new(WatchStream::new(rx).map(|snapshot| Event::default().json_data(snapshot))).keep_alive(KeepAlive::default())
+}
+
+fn router(rx: watch::Receiver<Snapshot>) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/events", get(events))
+        .with_state(rx)
+}
+
+/// Runs until the server state can no longer be updated or the status API can no longer be served.
+pub(crate) async fn run(http_client: reqwest::Client, config: Config) -> Result<(), Error> {
+    let (tx, rx) = watch::channel(Snapshot::new(0, &config, &None));
+    let listener = TcpListener::bind(config.daemon.address).await?;
+    log::info!("serving status API at http://{}", config.daemon.address);
+    let mut generation = 0;
+    let mut on_update = move |state: &SharedState| {
+        generation += 1;
+        tx.send_replace(Snapshot::new(generation, &config, state));
+    };
+    tokio::select! {
+        res = crate::maintain_inner(&http_client, Default::default(), &mut on_update) => res?,
+        res = axum::serve(listener, router(rx)) => res?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::test_util,
+        super::*,
+    };
+
+    fn loading(generation: u64) -> Snapshot {
+        Snapshot { generation, status: Status::Loading }
+    }
+
+    #[tokio::test]
+    async fn long_poll_wakes_up() {
+        let (tx, rx) = watch::channel(loading(0));
+        let base_url = test_util::serve(router(rx)).await;
+        let http_client = test_util::http_client();
+        let mut request = tokio::spawn(async move {
+            http_client.get(format!("{base_url}/status?after=0")).send().await?.error_for_status()?.text().await
+        });
+        assert!(timeout(Duration::from_millis(200), &mut request).await.is_err(), "long poll returned before an update");
+        tx.send_replace(loading(1));
+        let body = timeout(Duration::from_secs(5), request).await.unwrap().unwrap().unwrap();
+        assert_eq!(body, r#"{"generation":1,"status":"loading"}"#);
+    }
+
+    #[tokio::test]
+    async fn long_poll_ahead() {
+        let (_tx, rx) = watch::channel(loading(3));
+        let base_url = test_util::serve(router(rx)).await;
+        let response = timeout(Duration::from_secs(5), test_util::http_client().get(format!("{base_url}/status?after=7")).send()).await.unwrap().unwrap();
+        assert_eq!(response.error_for_status().unwrap().text().await.unwrap(), r#"{"generation":3,"status":"loading"}"#);
+    }
+
+    #[tokio::test]
+    async fn events_start_with_current_state() {
+        let (tx, rx) = watch::channel(loading(0));
+        let base_url = test_util::serve(router(rx)).await;
+        let mut response = test_util::http_client().get(format!("{base_url}/events")).send().await.unwrap().error_for_status().unwrap();
+        let first = timeout(Duration::from_secs(5), response.chunk()).await.unwrap().unwrap().unwrap();
+        assert_eq!(&first[..], b"data: {\"generation\":0,\"status\":\"loading\"}\n\n");
+        tx.send_replace(loading(1));
+        let second = timeout(Duration::from_secs(5), response.chunk()).await.unwrap().unwrap().unwrap();
+        assert_eq!(&second[..], b"data: {\"generation\":1,\"status\":\"loading\"}\n\n");
+    }
+}
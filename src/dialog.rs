@@ -0,0 +1,21 @@
+//! Reporting errors that can't be shown in a launch window.
+//!
+//! On Windows, these are shown as message boxes. On other platforms, they're printed to stderr.
+
+#[cfg(windows)] use native_windows_gui as nwg;
+
+pub(crate) fn error(title: &str, content: &str) {
+    #[cfg(windows)] nwg::error_message(title, content);
+    #[cfg(not(windows))] eprintln!("{title}: {content}");
+}
+
+/// Like [`error`], but exits the process afterwards.
+pub(crate) fn fatal(title: &str, content: &str) -> ! {
+    #[cfg(windows)] {
+        nwg::fatal_message(title, content)
+    }
+    #[cfg(not(windows))] {
+        eprintln!("{title}: {content}");
+        std::process::exit(1)
+    }
+}
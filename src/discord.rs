@@ -0,0 +1,313 @@
+//! Showing the Minecraft session as Discord Rich Presence, using the local Discord client's IPC socket.
+//!
+//! Messages are framed as a little-endian opcode and payload length followed by the JSON payload.
+
+use {
+    std::{
+        env,
+        path::PathBuf,
+        process,
+    },
+    chrono::prelude::*,
+    interprocess::local_socket::{
+        GenericFilePath,
+        ToFsName as _,
+        tokio::Stream,
+        traits::tokio::Stream as _,
+    },
+    serde::Serialize,
+    serde_json::Value,
+    tokio::io::{
+        self,
+        AsyncReadExt as _,
+        AsyncWriteExt as _,
+    },
+    crate::{
+        WorldStatus,
+        config,
+    },
+};
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const OP_CLOSE: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Utf8(#[from] std::str::Utf8Error),
+    #[error("Discord closed the connection: {0}")]
+    Closed(String),
+    #[error("Discord returned an error: {0}")]
+    Discord(String),
+    #[error("Discord doesn't seem to be running")]
+    NotRunning,
+    #[error("unexpected message from Discord with opcode {0}")]
+    Opcode(u32),
+}
+
+/// What's shown on the user's Discord profile.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Activity {
+    details: String,
+    state: String,
+    timestamps: Timestamps,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Timestamps {
+    /// In milliseconds since the Unix epoch.
+    start: i64,
+}
+
+impl Activity {
+    pub(crate) fn new(world: &str, status: Option<&WorldStatus>) -> Self {
+        let version = status.and_then(|status| status.version.as_deref());
+        let players = status.map_or(0, |status| status.list.len());
+        Self {
+            details: format!("Playing on {world}"),
+            state: match (version, players) {
+                (Some(version), 1) => format!("Minecraft {version}, 1 player online"),
+                (Some(version), _) => format!("Minecraft {version}, {players} players online"),
+                (None, 1) => format!("1 player online"),
+                (None, _) => format!("{players} players online"),
+            },
+            timestamps: Timestamps { start: Utc::now().timestamp_millis() },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Handshake<'a> {
+    v: u8,
+    client_id: &'a str,
+}
+
+#[derive(Serialize)]
+struct SetActivity<'a> {
+    cmd: &'static str,
+    args: SetActivityArgs<'a>,
+    nonce: String,
+}
+
+#[derive(Serialize)]
+struct SetActivityArgs<'a> {
+    pid: u32,
+    /// `None` clears the activity.
+    activity: Option<&'a Activity>,
+}
+
+/// The socket paths the Discord client may be listening on, in order of preference.
+fn ipc_paths(config: &config::Discord) -> Vec<PathBuf> {
+    if let Some(ref ipc_path) = config.ipc_path {
+        return vec![ipc_path.clone()]
+    }
+    if cfg!(windows) {
+        (0..10).map(|i| PathBuf::from(format!(r"\\.\pipe\discord-ipc-{i}"))).collect()
+    } else {
+        let base = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].into_iter().find_map(env::var_os).map_or_else(|| PathBuf::from("/tmp"), PathBuf::from);
+        // the Flatpak and Snap versions of Discord put the socket in a subfolder
+        ["", "app/com.discordapp.Discord", "snap.discord"].into_iter()
+            .flat_map(|subdir| (0..10).map(move |i| (subdir, i)))
+            .map(|(subdir, i)| base.join(subdir).join(format!("discord-ipc-{i}")))
+            .collect()
+    }
+}
+
+async fn send(stream: &mut Stream, opcode: u32, payload: &impl Serialize) -> Result<(), Error> {
+    let payload = serde_json::to_string_pretty(payload)?;
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&opcode.to_le_bytes());
+    frame.extend_from_slice(&u32::try_from(payload.len()).expect("Discord IPC message too long").to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    stream.write_all(&frame).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Receives a response to a handshake or command, failing if it's an error.
+async fn recv(stream: &mut Stream) -> Result<Value, Error> {
+    let opcode = stream.read_u32_le().await?;
+    let len = stream.read_u32_le().await?;
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf).await?;
+    let payload = serde_json::from_str::<Value>(std::str::from_utf8(&buf)?)?;
+    match opcode {
+        OP_FRAME => if payload["evt"] == "ERROR" {
+            Err(Error::Discord(payload["data"]["message"].as_str().unwrap_or_default().to_owned()))
+        } else {
+            Ok(payload)
+        },
+        OP_CLOSE => Err(Error::Closed(payload["message"].as_str().unwrap_or_default().to_owned())),
+        _ => Err(Error::Opcode(opcode)),
+    }
+}
+
+/// A connection to the Discord client which shows an activity. Discord removes the activity once the connection is closed, but [`Presence::clear`] does so explicitly.
+pub(crate) struct Presence {
+    stream: Stream,
+    nonce: u64,
+}
+
+impl Presence {
+    async fn connect(config: &config::Discord) -> Result<Self, Error> {
+        let mut stream = None;
+        for path in ipc_paths(config) {
+            match Stream::connect(path.as_path().to_fs_name::<GenericFilePath>()?).await {
+                Ok(connection) => {
+                    log::debug!("connected to Discord at {}", path.display());
+                    stream = Some(connection);
+                    break
+                }
+                Err(e) => log::trace!("failed to connect to Discord at {}: {e}", path.display()),
+            }
+        }
+        let mut stream = stream.ok_or(Error::NotRunning)?;
+        send(&mut stream, OP_HANDSHAKE, &Handshake {
+            v: 1,
+            client_id: &config.client_id,
+        }).await?;
+        recv(&mut stream).await?;
+        Ok(Self { stream, nonce: 0 })
+    }
+
+    async fn set_activity(&mut self, activity: Option<&Activity>) -> Result<(), Error> {
+        self.nonce += 1;
+        send(&mut self.stream, OP_FRAME, &SetActivity {
+            cmd: "SET_ACTIVITY",
+            args: SetActivityArgs {
+                pid: process::id(),
+                activity,
+            },
+            nonce: self.nonce.to_string(),
+        }).await?;
+        recv(&mut self.stream).await?;
+        Ok(())
+    }
+
+    /// Shows the activity. Errors are logged rather than returned since Rich Presence isn't essential for launching.
+    pub(crate) async fn show(config: &config::Discord, activity: &Activity) -> Option<Self> {
+        let result = async {
+            let mut presence = Self::connect(config).await?;
+            presence.set_activity(Some(activity)).await?;
+            Ok::<_, Error>(presence)
+        }.await;
+        match result {
+            Ok(presence) => {
+                log::info!("showing Discord Rich Presence");
+                Some(presence)
+            }
+            Err(e) => {
+                log::warn!("failed to show Discord Rich Presence: {e} ({e:?})");
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn clear(mut self) {
+        match self.set_activity(None).await {
+            Ok(()) => log::info!("cleared Discord Rich Presence"),
+            Err(e) => log::warn!("failed to clear Discord Rich Presence: {e} ({e:?})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        interprocess::local_socket::{
+            ListenerOptions,
+            traits::tokio::Listener as _,
+        },
+        crate::test_util,
+        super::*,
+    };
+
+    fn ipc_path(name: &str) -> PathBuf {
+        if cfg!(windows) {
+            PathBuf::from(format!(r"\\.\pipe\wurstmineberg-test-{}-{name}", process::id()))
+        } else {
+            test_util::temp_dir(name).join("discord-ipc-0")
+        }
+    }
+
+    async fn read_frame(stream: &mut Stream) -> (u32, Value) {
+        let opcode = stream.read_u32_le().await.unwrap();
+        let len = stream.read_u32_le().await.unwrap();
+        let mut buf = vec![0; len as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+        (opcode, serde_json::from_str(std::str::from_utf8(&buf).unwrap()).unwrap())
+    }
+
+    fn response(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn world(players: usize) -> WorldStatus {
+        WorldStatus {
+            address: None,
+            loader: None,
+            list: (0..players).map(|i| crate::people::Uid::WmbId(format!("player{i}"))).collect(),
+            running: true,
+            version: Some(format!("1.21.4")),
+        }
+    }
+
+    #[test]
+    fn activity_state() {
+        assert_eq!(Activity::new("wurstmineberg", Some(&world(1))).state, "Minecraft 1.21.4, 1 player online");
+        assert_eq!(Activity::new("wurstmineberg", Some(&world(3))).state, "Minecraft 1.21.4, 3 players online");
+        assert_eq!(Activity::new("creative", None).state, "0 players online");
+        assert_eq!(Activity::new("creative", None).details, "Playing on creative");
+    }
+
+    #[tokio::test]
+    async fn show_and_clear() {
+        let path = ipc_path("discord-show");
+        let listener = ListenerOptions::new().name(path.as_path().to_fs_name::<GenericFilePath>().unwrap()).create_tokio().unwrap();
+        let server = tokio::spawn(async move {
+            let mut stream = listener.accept().await.unwrap();
+            let (opcode, handshake) = read_frame(&mut stream).await;
+            assert_eq!(opcode, OP_HANDSHAKE);
+            assert_eq!(handshake["v"], 1);
+            assert_eq!(handshake["client_id"], "1234");
+            send(&mut stream, OP_FRAME, &response(r#"{"cmd": "DISPATCH", "evt": "READY", "data": {"v": 1}}"#)).await.unwrap();
+            let (opcode, set_activity) = read_frame(&mut stream).await;
+            assert_eq!(opcode, OP_FRAME);
+            assert_eq!(set_activity["cmd"], "SET_ACTIVITY");
+            assert_eq!(set_activity["args"]["pid"], process::id());
+            assert_eq!(set_activity["args"]["activity"]["state"], "Minecraft 1.21.4, 1 player online");
+            send(&mut stream, OP_FRAME, &response(r#"{"cmd": "SET_ACTIVITY", "evt": null, "nonce": "1"}"#)).await.unwrap();
+            let (_, clear) = read_frame(&mut stream).await;
+            assert!(clear["args"]["activity"].is_null());
+            assert_eq!(clear["nonce"], "2");
+            send(&mut stream, OP_FRAME, &response(r#"{"cmd": "SET_ACTIVITY", "evt": null, "nonce": "2"}"#)).await.unwrap();
+        });
+        let config = config::Discord { client_id: format!("1234"), ipc_path: Some(path) };
+        let presence = Presence::show(&config, &Activity::new("wurstmineberg", Some(&world(1)))).await.expect("failed to show presence");
+        presence.clear().await;
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn discord_error() {
+        let path = ipc_path("discord-error");
+        let listener = ListenerOptions::new().name(path.as_path().to_fs_name::<GenericFilePath>().unwrap()).create_tokio().unwrap();
+        let server = tokio::spawn(async move {
+            let mut stream = listener.accept().await.unwrap();
+            read_frame(&mut stream).await;
+            send(&mut stream, OP_CLOSE, &response(r#"{"code": 4000, "message": "Invalid Client ID"}"#)).await.unwrap();
+        });
+        let config = config::Discord { client_id: format!("invalid"), ipc_path: Some(path) };
+        assert!(matches!(Presence::connect(&config).await, Err(Error::Closed(message)) if message == "Invalid Client ID"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn not_running() {
+        let config = config::Discord { client_id: format!("1234"), ipc_path: Some(ipc_path("discord-not-running")) };
+        assert!(matches!(Presence::connect(&config).await, Err(Error::NotRunning)));
+        assert!(Presence::show(&config, &Activity::new("wurstmineberg", None)).await.is_none());
+    }
+}
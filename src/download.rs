@@ -0,0 +1,249 @@
+//! Fetching files from URLs or local paths, with hash verification.
+
+use {
+    std::{
+        collections::BTreeSet,
+        io,
+        path::{
+            Component,
+            Path,
+            PathBuf,
+        },
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    sha1::Sha1,
+    sha2::{
+        Digest as _,
+        Sha256,
+        Sha512,
+    },
+    tokio::sync::watch,
+    wheel::{
+        fs,
+        traits::{
+            IoResultExt as _,
+            ReqwestResponseExt as _,
+        },
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Url(#[from] url::ParseError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("{location} has {algorithm} hash {found} but should have {expected}")]
+    HashMismatch {
+        location: String,
+        algorithm: HashFormat,
+        expected: String,
+        found: String,
+    },
+    #[error("refusing to download from {0} since only HTTPS URLs are allowed")]
+    NotHttps(String),
+    #[error("refusing to write {0} since it's outside the target folder")]
+    PathTraversal(String),
+    #[error("unsupported hash format: {0}")]
+    UnsupportedHashFormat(String),
+}
+
+/// A file that's either downloaded from a URL or read from a local path.
+#[derive(Debug, Clone)]
+pub(crate) enum Location {
+    Url(url::Url),
+    Path(PathBuf),
+}
+
+impl Location {
+    /// Interprets `http://` and `https://` URLs as URLs and anything else as a local path.
+    pub(crate) fn parse(location: &str) -> Result<Self, Error> {
+        Ok(if location.starts_with("https://") || location.starts_with("http://") {
+            Self::Url(location.parse()?)
+        } else {
+            Self::Path(PathBuf::from(location))
+        })
+    }
+
+    /// Only accepts `https://` URLs, for locations listed in files that may come from elsewhere, so these can't make the app read local files.
+    ///
+    /// In tests, `http://` URLs on the loopback interface are also accepted so local stand-in servers can be used.
+    pub(crate) fn parse_https(location: &str) -> Result<Self, Error> {
+        if location.starts_with("https://") || cfg!(test) && location.starts_with("http://127.0.0.1:") {
+            Ok(Self::Url(location.parse()?))
+        } else {
+            Err(Error::NotHttps(location.to_owned()))
+        }
+    }
+
+    /// Resolves a `/`-separated path relative to the folder containing this file.
+    pub(crate) fn sibling(&self, relative: &str) -> Result<Self, Error> {
+        Ok(match self {
+            Self::Url(url) => Self::Url(url.join(relative)?),
+            Self::Path(path) => Self::Path(path.parent().unwrap_or(Path::new(".")).join(relative_path(relative)?)),
+        })
+    }
+
+    /// Resolves a `/`-separated path relative to this location, which is treated as a folder even if its URL doesn't end with `/`.
+    pub(crate) fn join(&self, relative: &str) -> Result<Self, Error> {
+        Ok(match self {
+            Self::Url(url) => if url.path().ends_with('/') {
+                Self::Url(url.join(relative)?)
+            } else {
+                let mut base = url.clone();
+                base.set_path(&format!("{}/", url.path()));
+                Self::Url(base.join(relative)?)
+            },
+            Self::Path(path) => Self::Path(path.join(relative_path(relative)?)),
+        })
+    }
+
+    pub(crate) async fn read(&self, http_client: &reqwest::Client) -> Result<Vec<u8>, Error> {
+        Ok(match self {
+            Self::Url(url) => http_client.get(url.clone())
+                .send().await?
+                .detailed_error_for_status().await?
+                .bytes().await?
+                .to_vec(),
+            Self::Path(path) => fs::read(path).await?,
+        })
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(url) => url.fmt(f),
+            Self::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HashFormat {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashFormat {
+    pub(crate) fn parse(format: &str) -> Result<Self, Error> {
+        match format {
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            "sha512" => Ok(Self::Sha512),
+            _ => Err(Error::UnsupportedHashFormat(format.to_owned())),
+        }
+    }
+
+    pub(crate) fn hash(&self, data: &[u8]) -> String {
+        match self {
+            Self::Sha1 => hex(&Sha1::digest(data)),
+            Self::Sha256 => hex(&Sha256::digest(data)),
+            Self::Sha512 => hex(&Sha512::digest(data)),
+        }
+    }
+
+    pub(crate) fn verify(&self, location: &impl ToString, data: &[u8], expected: &str) -> Result<(), Error> {
+        let found = self.hash(data);
+        if found.eq_ignore_ascii_case(expected) {
+            Ok(())
+        } else {
+            Err(Error::HashMismatch {
+                location: location.to_string(),
+                algorithm: *self,
+                expected: expected.to_owned(),
+                found,
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for HashFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha1 => write!(f, "SHA-1"),
+            Self::Sha256 => write!(f, "SHA-256"),
+            Self::Sha512 => write!(f, "SHA-512"),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Converts a `/`-separated path from a modpack into a relative path, rejecting anything that would escape the folder it's relative to.
+pub(crate) fn relative_path(path: &str) -> Result<PathBuf, Error> {
+    let relative = PathBuf::from_iter(path.split('/').filter(|segment| !segment.is_empty() && *segment != "."));
+    if relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        Ok(relative)
+    } else {
+        Err(Error::PathTraversal(path.to_owned()))
+    }
+}
+
+/// Returns [`Error::Cancelled`] if `cancel` has been set. Installers call this between files rather than being dropped, so cancelling never leaves a file partially written.
+pub(crate) fn check_cancelled(cancel: &watch::Receiver<bool>) -> Result<(), Error> {
+    if *cancel.borrow() { Err(Error::Cancelled) } else { Ok(()) }
+}
+
+/// Returns the contents of `location` after checking it against the expected hash.
+pub(crate) async fn fetch_verified(http_client: &reqwest::Client, location: &Location, format: HashFormat, expected: &str) -> Result<Vec<u8>, Error> {
+    let data = location.read(http_client).await?;
+    format.verify(location, &data, expected)?;
+    Ok(data)
+}
+
+/// Makes sure the file at `path` has the expected hash, downloading it from `location` if it doesn't.
+///
+/// Returns whether the file was downloaded.
+pub(crate) async fn install_verified(http_client: &reqwest::Client, location: &Location, path: &Path, format: HashFormat, expected: &str) -> Result<bool, Error> {
+    if fs::exists(path).await? && format.hash(&fs::read(path).await?).eq_ignore_ascii_case(expected) {
+        return Ok(false)
+    }
+    let data = fetch_verified(http_client, location, format, expected).await?;
+    write_atomic(path, data).await?;
+    Ok(true)
+}
+
+/// Writes the file via a temporary file in the same folder, so an interrupted write never leaves an incomplete file at `path`.
+pub(crate) async fn write_atomic(path: &Path, data: impl AsRef<[u8]>) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let temp_path = path.with_file_name(format!(".{}.part", path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default()));
+    fs::write(&temp_path, data).await?;
+    tokio::fs::rename(&temp_path, path).await.at(&temp_path)?;
+    Ok(())
+}
+
+/// Lists the files installed into a folder from a modpack, so files removed from the pack can be deleted without touching files the user added.
+#[derive(Default, Deserialize, Serialize)]
+struct InstalledFiles {
+    files: BTreeSet<String>,
+}
+
+/// Records `files` as installed into `dir` from a modpack, deleting any files that were recorded by the previous call with the same `record_name` but are no longer included.
+pub(crate) async fn replace_installed(dir: &Path, record_name: &str, files: BTreeSet<String>) -> Result<(), Error> {
+    let record_path = dir.join(record_name);
+    let previous = fs::read_json::<InstalledFiles>(&record_path).await.missing_ok()?;
+    for file in previous.files.difference(&files) {
+        let path = dir.join(relative_path(file)?);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).at(path).map_err(Error::from),
+        }
+    }
+    let mut buf = serde_json::to_string_pretty(&InstalledFiles { files })?;
+    buf.push('\n');
+    fs::create_dir_all(dir).await?;
+    fs::write(record_path, buf).await?;
+    Ok(())
+}
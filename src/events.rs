@@ -0,0 +1,221 @@
+//! Changes in the server state between two updates, e.g. to trigger hooks.
+
+use {
+    std::collections::HashMap,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    crate::{
+        State,
+        people::{
+            Person,
+            Uid,
+        },
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Kind {
+    Join,
+    Leave,
+    ServerUp,
+    ServerDown,
+    VersionChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub(crate) enum Event {
+    Join {
+        world: String,
+        player: Player,
+    },
+    Leave {
+        world: String,
+        player: Player,
+    },
+    ServerUp {
+        world: String,
+    },
+    ServerDown {
+        world: String,
+    },
+    VersionChange {
+        world: String,
+        old_version: Option<String>,
+        new_version: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Player {
+    pub(crate) uid: String,
+    /// The player's display name, or their Wurstmineberg ID or Discord snowflake if they don't have one.
+    pub(crate) name: String,
+}
+
+impl Player {
+    fn new(people: &HashMap<Uid, Person>, uid: &Uid) -> Self {
+        Self {
+            uid: uid.to_string(),
+            name: people.get(uid).and_then(|person| person.name.clone()).unwrap_or_else(|| uid.to_string()),
+        }
+    }
+}
+
+impl Event {
+    pub(crate) fn kind(&self) -> Kind {
+        match self {
+            Self::Join { .. } => Kind::Join,
+            Self::Leave { .. } => Kind::Leave,
+            Self::ServerUp { .. } => Kind::ServerUp,
+            Self::ServerDown { .. } => Kind::ServerDown,
+            Self::VersionChange { .. } => Kind::VersionChange,
+        }
+    }
+
+    pub(crate) fn world(&self) -> &str {
+        match self {
+            Self::Join { world, .. } | Self::Leave { world, .. } | Self::ServerUp { world } | Self::ServerDown { world } | Self::VersionChange { world, .. } => world,
+        }
+    }
+}
+
+/// The events that happened between two server states, sorted by world name.
+///
+/// Worlds that appear or disappear between the states are treated as having been offline without players.
+pub(crate) fn diff((_, old_statuses): &State, (people, new_statuses): &State) -> Vec<Event> {
+    let mut worlds = old_statuses.keys().chain(new_statuses.keys()).collect::<Vec<_>>();
+    worlds.sort();
+    worlds.dedup();
+    let mut events = Vec::default();
+    for world in worlds {
+        let old = old_statuses.get(world);
+        let new = new_statuses.get(world);
+        let old_running = old.is_some_and(|status| status.running);
+        let new_running = new.is_some_and(|status| status.running);
+        if !old_running && new_running {
+            events.push(Event::ServerUp { world: world.clone() });
+        }
+        let old_version = old.and_then(|status| status.version.clone());
+        let new_version = new.and_then(|status| status.version.clone());
+        // the API may not report a version while the world is offline
+        if old_version != new_version && old_version.is_some() && new_version.is_some() {
+            events.push(Event::VersionChange { world: world.clone(), old_version, new_version });
+        }
+        let old_list = old.map(|status| &*status.list).unwrap_or_default();
+        let new_list = new.map(|status| &*status.list).unwrap_or_default();
+        for uid in old_list {
+            if !new_list.contains(uid) {
+                events.push(Event::Leave { world: world.clone(), player: Player::new(people, uid) });
+            }
+        }
+        for uid in new_list {
+            if !old_list.contains(uid) {
+                events.push(Event::Join { world: world.clone(), player: Player::new(people, uid) });
+            }
+        }
+        if old_running && !new_running {
+            events.push(Event::ServerDown { world: world.clone() });
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::WorldStatus,
+        super::*,
+    };
+
+    fn world(running: bool, version: Option<&str>, list: &[&str]) -> WorldStatus {
+        WorldStatus {
+            address: None,
+            loader: None,
+            list: list.iter().map(|&uid| Uid::WmbId(uid.to_owned())).collect(),
+            version: version.map(str::to_owned),
+            running,
+        }
+    }
+
+    fn state(worlds: impl IntoIterator<Item = (&'static str, WorldStatus)>) -> State {
+        let people = HashMap::from([(Uid::WmbId(format!("fenhl")), Person { name: Some(format!("Fenhl")) })]);
+        (people, worlds.into_iter().map(|(world_name, status)| (world_name.to_owned(), status)).collect())
+    }
+
+    fn player(uid: &str, name: &str) -> Player {
+        Player { uid: uid.to_owned(), name: name.to_owned() }
+    }
+
+    #[test]
+    fn no_change() {
+        let state = state([("wurstmineberg", world(true, Some("1.21.4"), &["fenhl"]))]);
+        assert!(diff(&state, &state).is_empty());
+    }
+
+    #[test]
+    fn join() {
+        let old = state([("wurstmineberg", world(true, Some("1.21.4"), &[]))]);
+        let new = state([("wurstmineberg", world(true, Some("1.21.4"), &["fenhl", "alice"]))]);
+        assert_eq!(diff(&old, &new), [
+            Event::Join { world: format!("wurstmineberg"), player: player("fenhl", "Fenhl") },
+            // people without a name are identified by their ID
+            Event::Join { world: format!("wurstmineberg"), player: player("alice", "alice") },
+        ]);
+    }
+
+    #[test]
+    fn leave() {
+        let old = state([("wurstmineberg", world(true, Some("1.21.4"), &["fenhl", "alice"]))]);
+        let new = state([("wurstmineberg", world(true, Some("1.21.4"), &["alice"]))]);
+        let events = diff(&old, &new);
+        assert_eq!(events, [Event::Leave { world: format!("wurstmineberg"), player: player("fenhl", "Fenhl") }]);
+        assert_eq!(events[0].kind(), Kind::Leave);
+        assert_eq!(events[0].world(), "wurstmineberg");
+    }
+
+    #[test]
+    fn server_up() {
+        let old = state([("wurstmineberg", world(false, None, &[]))]);
+        let new = state([("wurstmineberg", world(true, Some("1.21.4"), &["fenhl"]))]);
+        // no version change since the offline world didn't report a version
+        assert_eq!(diff(&old, &new), [
+            Event::ServerUp { world: format!("wurstmineberg") },
+            Event::Join { world: format!("wurstmineberg"), player: player("fenhl", "Fenhl") },
+        ]);
+        // worlds that appear are treated as having been offline
+        let new_world = state([("wurstmineberg", world(false, None, &[])), ("creative", world(true, Some("1.21.4"), &[]))]);
+        assert_eq!(diff(&old, &new_world), [Event::ServerUp { world: format!("creative") }]);
+    }
+
+    #[test]
+    fn server_down() {
+        let old = state([("wurstmineberg", world(true, Some("1.21.4"), &["fenhl"]))]);
+        let new = state([("wurstmineberg", world(false, Some("1.21.4"), &[]))]);
+        // players leave before the server goes down
+        assert_eq!(diff(&old, &new), [
+            Event::Leave { world: format!("wurstmineberg"), player: player("fenhl", "Fenhl") },
+            Event::ServerDown { world: format!("wurstmineberg") },
+        ]);
+        // worlds that disappear are treated as going offline
+        assert_eq!(diff(&old, &state([])), [
+            Event::Leave { world: format!("wurstmineberg"), player: player("fenhl", "Fenhl") },
+            Event::ServerDown { world: format!("wurstmineberg") },
+        ]);
+    }
+
+    #[test]
+    fn version_change() {
+        let old = state([("creative", world(true, Some("1.21.1"), &[])), ("wurstmineberg", world(true, Some("1.21.1"), &[]))]);
+        let new = state([("creative", world(true, Some("1.21.1"), &[])), ("wurstmineberg", world(true, Some("1.21.4"), &[]))]);
+        let events = diff(&old, &new);
+        assert_eq!(events, [Event::VersionChange { world: format!("wurstmineberg"), old_version: Some(format!("1.21.1")), new_version: Some(format!("1.21.4")) }]);
+        assert_eq!(events[0].kind(), Kind::VersionChange);
+        // a version missing while offline isn't a change
+        let offline = state([("creative", world(true, Some("1.21.1"), &[])), ("wurstmineberg", world(false, None, &[]))]);
+        assert_eq!(diff(&old, &offline), [Event::ServerDown { world: format!("wurstmineberg") }]);
+    }
+}
@@ -0,0 +1,381 @@
+//! Direct access to the config file of <https://github.com/gorilla-devs/ferium>, so profiles can be inspected and switched without parsing the CLI's output.
+
+use {
+    std::{
+        collections::BTreeMap,
+        env,
+        io,
+        iter,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    directories::BaseDirs,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    serde_json::Value as Json,
+    wheel::{
+        fs,
+        traits::IoResultExt as _,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("failed to find user folder")]
+    BaseDirs,
+    #[error("no profile named “{0}” in ferium config")]
+    UnknownProfile(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Data {
+    /// Index into `profiles`.
+    pub(crate) active_profile: usize,
+    pub(crate) profiles: Vec<Profile>,
+    #[serde(flatten)]
+    _extra: BTreeMap<String, Json>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Profile {
+    pub(crate) name: String,
+    /// The profile's `mods` folder.
+    pub(crate) output_dir: PathBuf,
+    /// Used by ferium versions before 4.6.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    game_version: Option<String>,
+    /// Used by ferium 4.6 and later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filters: Option<Vec<Json>>,
+    #[serde(flatten)]
+    _extra: BTreeMap<String, Json>,
+}
+
+impl Profile {
+    /// The profile's Minecraft version, if it requires a specific one.
+    pub(crate) fn game_version(&self) -> Option<&str> {
+        if let Some(ref game_version) = self.game_version {
+            Some(game_version)
+        } else {
+            self.filters.iter().flatten().find_map(|filter| filter.get("GameVersionStrict")?.as_array()?.first()?.as_str())
+        }
+    }
+
+    pub(crate) fn set_game_version(&mut self, version: &str) {
+        if let Some(ref mut filters) = self.filters {
+            filters.retain(|filter| filter.get("GameVersionStrict").is_none() && filter.get("GameVersionMinor").is_none());
+            filters.push(Json::Object(iter::once((format!("GameVersionStrict"), Json::Array(vec![Json::String(version.to_owned())]))).collect()));
+        } else {
+            self.game_version = Some(version.to_owned());
+        }
+    }
+
+    /// The folder containing the profile's `mods` folder, i.e. the game folder.
+    pub(crate) fn game_dir(&self) -> PathBuf {
+        let mut dir = self.output_dir.clone();
+        dir.pop();
+        dir
+    }
+}
+
+impl Data {
+    pub(crate) async fn load(path: &Path) -> Result<Self, Error> {
+        Ok(fs::read_json(path).await?)
+    }
+
+    pub(crate) async fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut buf = serde_json::to_string_pretty(self)?;
+        buf.push('\n');
+        fs::write(path, buf).await?;
+        Ok(())
+    }
+
+    pub(crate) fn profile_index(&self, name: &str) -> Result<usize, Error> {
+        self.profiles.iter().position(|profile| profile.name == name).ok_or_else(|| Error::UnknownProfile(name.to_owned()))
+    }
+}
+
+/// The location of ferium's config file, respecting the `ferium.configFile` entry of our config.
+pub(crate) fn config_path(config: &crate::config::Ferium) -> Result<PathBuf, Error> {
+    Ok(if let Some(ref config_file) = config.config_file {
+        config_file.clone()
+    } else if let Some(config_file) = env::var_os("FERIUM_CONFIG_FILE") {
+        PathBuf::from(config_file)
+    } else {
+        // ferium uses this path on all platforms
+        BaseDirs::new().ok_or(Error::BaseDirs)?.home_dir().join(".config").join("ferium").join("config.json")
+    })
+}
+
+/// Ferium's active profile, temporarily switched to a different one.
+///
+/// The previous profile is restored by [`ProfileSwitch::restore`] or, if that isn't reached (e.g. because a later step failed), when this is dropped.
+pub(crate) struct ProfileSwitch {
+    config_path: PathBuf,
+    previous_profile: Option<usize>,
+}
+
+impl ProfileSwitch {
+    pub(crate) async fn new(config_path: PathBuf, data: &mut Data, profile_index: usize) -> Result<Self, Error> {
+        let previous_profile = data.active_profile;
+        data.active_profile = profile_index;
+        data.save(&config_path).await?;
+        Ok(Self { previous_profile: Some(previous_profile), config_path })
+    }
+
+    pub(crate) async fn restore(mut self) -> Result<(), Error> {
+        if let Some(previous_profile) = self.previous_profile.take() {
+            // reload since `ferium upgrade` may have modified the config
+            let mut data = Data::load(&self.config_path).await?;
+            data.active_profile = previous_profile;
+            data.save(&self.config_path).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ProfileSwitch {
+    fn drop(&mut self) {
+        if let Some(previous_profile) = self.previous_profile.take() {
+            let config_path = self.config_path.clone();
+            let restore = move || if let Err(e) = restore_blocking(&config_path, previous_profile) {
+                log::error!("failed to restore ferium profile: {e} ({e:?})");
+            };
+            // can't await here, so keep the file IO off the async runtime's worker threads
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn_blocking(restore);
+            } else {
+                restore();
+            }
+        }
+    }
+}
+
+fn restore_blocking(config_path: &Path, previous_profile: usize) -> Result<(), Error> {
+    let mut data = serde_json::from_str::<Data>(&std::fs::read_to_string(config_path).at(config_path)?)?;
+    data.active_profile = previous_profile;
+    let mut buf = serde_json::to_string_pretty(&data)?;
+    buf.push('\n');
+    std::fs::write(config_path, buf).at(config_path)?;
+    Ok(())
+}
+
+/// A copy of a `mods` folder, taken before running `ferium upgrade` so that a failed upgrade can be rolled back.
+///
+/// If neither [`ModsSnapshot::rollback`] nor [`ModsSnapshot::discard`] is reached (e.g. because the launch was cancelled before the upgrade started), the snapshot is discarded when this is dropped.
+pub(crate) struct ModsSnapshot {
+    mods_dir: PathBuf,
+    /// `None` if the `mods` folder didn't exist when the snapshot was taken.
+    snapshot_dir: Option<PathBuf>,
+}
+
+impl ModsSnapshot {
+    pub(crate) async fn take(mods_dir: PathBuf) -> Result<Self, Error> {
+        let snapshot_dir = if fs::exists(&mods_dir).await? {
+            let snapshot_dir = mods_dir.with_file_name(".mods-wurstmineberg-snapshot");
+            let (src, dst) = (mods_dir.clone(), snapshot_dir.clone());
+            tokio::task::spawn_blocking(move || {
+                match std::fs::remove_dir_all(&dst) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e).at(&dst),
+                }
+                copy_dir(&src, &dst)
+            }).await??;
+            Some(snapshot_dir)
+        } else {
+            None
+        };
+        Ok(Self { mods_dir, snapshot_dir })
+    }
+
+    /// The folder containing the copy of the `mods` folder, if it existed.
+    pub(crate) fn snapshot_dir(&self) -> Option<&Path> {
+        self.snapshot_dir.as_deref()
+    }
+
+    /// Replaces the `mods` folder with the snapshot. If this fails, the snapshot is kept.
+    pub(crate) async fn rollback(mut self) -> Result<(), Error> {
+        let snapshot_dir = self.snapshot_dir.take();
+        if fs::exists(&self.mods_dir).await? {
+            tokio::fs::remove_dir_all(&self.mods_dir).await.at(&self.mods_dir)?;
+        }
+        if let Some(ref snapshot_dir) = snapshot_dir {
+            tokio::fs::rename(snapshot_dir, &self.mods_dir).await.at(snapshot_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the snapshot, keeping the current `mods` folder.
+    pub(crate) async fn discard(mut self) -> Result<(), Error> {
+        if let Some(ref snapshot_dir) = self.snapshot_dir.take() {
+            tokio::fs::remove_dir_all(snapshot_dir).await.at(snapshot_dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ModsSnapshot {
+    fn drop(&mut self) {
+        if let Some(snapshot_dir) = self.snapshot_dir.take() {
+            let discard = move || if let Err(e) = std::fs::remove_dir_all(&snapshot_dir) {
+                log::error!("failed to discard mods snapshot at {}: {e} ({e:?})", snapshot_dir.display());
+            };
+            // can't await here, so keep the file IO off the async runtime's worker threads
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn_blocking(discard);
+            } else {
+                discard();
+            }
+        }
+    }
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), wheel::Error> {
+    std::fs::create_dir_all(dst).at(dst)?;
+    for entry in std::fs::read_dir(src).at(src)? {
+        let entry = entry.at(src)?;
+        let dst = dst.join(entry.file_name());
+        if entry.file_type().at(entry.path())?.is_dir() {
+            copy_dir(&entry.path(), &dst)?;
+        } else {
+            std::fs::copy(entry.path(), &dst).at(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::test_util,
+        super::*,
+    };
+
+    const OLD_CONFIG: &str = r#"{
+        "warn_for_unknown": true,
+        "active_profile": 1,
+        "profiles": [
+            {
+                "name": "Other",
+                "output_dir": "/home/user/.minecraft/mods",
+                "game_version": "1.19.4",
+                "mod_loader": "Fabric",
+                "mods": []
+            },
+            {
+                "name": "Wurstmineberg",
+                "output_dir": "/home/user/wurstmineberg/mods",
+                "game_version": "1.20.1",
+                "mod_loader": "Fabric",
+                "mods": [{"name": "Sodium"}, {"name": "Lithium"}]
+            }
+        ]
+    }"#;
+
+    const NEW_CONFIG: &str = r#"{
+        "active_profile": 0,
+        "profiles": [
+            {
+                "name": "Wurstmineberg",
+                "output_dir": "/home/user/wurstmineberg/mods",
+                "filters": [
+                    {"ModLoaderPrefer": ["Fabric", "Quilt"]},
+                    {"GameVersionStrict": ["1.21.1"]}
+                ],
+                "mods": [{"name": "Sodium"}]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn old_game_version() {
+        let mut data = serde_json::from_str::<Data>(OLD_CONFIG).unwrap();
+        let profile = &mut data.profiles[1];
+        assert_eq!(profile.game_version(), Some("1.20.1"));
+        profile.set_game_version("1.21.1");
+        assert_eq!(profile.game_version(), Some("1.21.1"));
+        assert!(profile.filters.is_none());
+    }
+
+    #[test]
+    fn filters_game_version() {
+        let mut data = serde_json::from_str::<Data>(NEW_CONFIG).unwrap();
+        let profile = &mut data.profiles[0];
+        assert_eq!(profile.game_version(), Some("1.21.1"));
+        profile.set_game_version("1.21.4");
+        assert_eq!(profile.game_version(), Some("1.21.4"));
+        assert!(profile.game_version.is_none());
+        let filters = profile.filters.as_ref().unwrap();
+        assert_eq!(filters.len(), 2, "the old version filter should be replaced");
+        assert!(filters[0].get("ModLoaderPrefer").is_some());
+    }
+
+    #[test]
+    fn no_game_version() {
+        let data = serde_json::from_str::<Data>(r#"{"active_profile": 0, "profiles": [{"name": "Any", "output_dir": "/mods", "filters": []}]}"#).unwrap();
+        assert_eq!(data.profiles[0].game_version(), None);
+    }
+
+    #[test]
+    fn game_dir() {
+        let data = serde_json::from_str::<Data>(OLD_CONFIG).unwrap();
+        assert_eq!(data.profiles[1].game_dir(), Path::new("/home/user/wurstmineberg"));
+    }
+
+    #[test]
+    fn profile_index() {
+        let data = serde_json::from_str::<Data>(OLD_CONFIG).unwrap();
+        assert_eq!(data.profile_index("Other").unwrap(), 0);
+        assert_eq!(data.profile_index("Wurstmineberg").unwrap(), 1);
+        assert!(matches!(data.profile_index("Missing"), Err(Error::UnknownProfile(name)) if name == "Missing"));
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let dir = test_util::temp_dir("ferium-round-trip");
+        let path = dir.join("config.json");
+        for config in [OLD_CONFIG, NEW_CONFIG] {
+            let data = serde_json::from_str::<Data>(config).unwrap();
+            data.save(&path).await.unwrap();
+            let reloaded = Data::load(&path).await.unwrap();
+            // fields we don't know about must be preserved so ferium keeps working
+            assert_eq!(serde_json::from_str::<Json>(&serde_json::to_string_pretty(&reloaded).unwrap()).unwrap(), serde_json::from_str::<Json>(config).unwrap());
+        }
+    }
+    #[tokio::test]
+    async fn snapshot_rollback() {
+        let mods_dir = test_util::temp_dir("ferium-rollback").join("mods");
+        std::fs::create_dir_all(&mods_dir).unwrap();
+        std::fs::write(mods_dir.join("old.jar"), "old").unwrap();
+        let snapshot = ModsSnapshot::take(mods_dir.clone()).await.unwrap();
+        let snapshot_dir = snapshot.snapshot_dir().unwrap().to_owned();
+        std::fs::remove_file(mods_dir.join("old.jar")).unwrap();
+        std::fs::write(mods_dir.join("new.jar"), "new").unwrap();
+        snapshot.rollback().await.unwrap();
+        assert!(mods_dir.join("old.jar").exists());
+        assert!(!mods_dir.join("new.jar").exists());
+        assert!(!snapshot_dir.exists());
+    }
+
+    #[test]
+    fn snapshot_discarded_on_drop() {
+        let mods_dir = test_util::temp_dir("ferium-snapshot-drop").join("mods");
+        std::fs::create_dir_all(&mods_dir).unwrap();
+        std::fs::write(mods_dir.join("old.jar"), "old").unwrap();
+        // dropped outside the runtime so the snapshot is discarded synchronously
+        let snapshot = tokio::runtime::Runtime::new().unwrap().block_on(ModsSnapshot::take(mods_dir.clone())).unwrap();
+        let snapshot_dir = snapshot.snapshot_dir().unwrap().to_owned();
+        assert!(snapshot_dir.join("old.jar").exists());
+        drop(snapshot);
+        assert!(!snapshot_dir.exists());
+        assert!(mods_dir.join("old.jar").exists());
+    }
+}
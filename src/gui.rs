@@ -1,14 +1,28 @@
 use {
     std::{
-        collections::HashMap,
+        collections::{
+            HashMap,
+            VecDeque,
+        },
+        fmt,
         hash::Hash,
-        io::prelude::*,
         iter,
-        path::PathBuf,
+        path::{
+            Path,
+            PathBuf,
+        },
         sync::Arc,
     },
-    futures::stream::StreamExt as _,
+    chrono::{
+        DateTime,
+        Local,
+    },
+    futures::stream::{
+        self,
+        StreamExt as _,
+    },
     iced::{
+        Length,
         Size,
         Subscription,
         Task,
@@ -18,193 +32,642 @@ use {
             icon,
         },
     },
-    native_windows_gui as nwg,
     tokio::{
         io,
-        process::Command,
+        process::{
+            Child,
+            Command,
+        },
         sync::{
             broadcast,
             mpsc,
+            watch,
         },
         task::JoinHandle,
     },
     tokio_stream::wrappers::{
         BroadcastStream,
         ReceiverStream,
+        UnboundedReceiverStream,
     },
     wheel::traits::{
-        AsyncCommandOutputExt as _,
         CommandExt as _,
         IoResultExt as _,
         SendResultExt as _,
     },
     crate::{
-        MAIN_WORLD,
         State,
-        config::Config,
+        auth,
+        command,
+        config::{
+            self,
+            Config,
+        },
+        discord,
+        ferium,
+        java,
+        launcher,
+        loader::{
+            self,
+            Loader,
+        },
+        mods,
+        mrpack,
+        native,
+        packwiz,
+        quick_play,
     },
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    LoadConfig,
+    GetServerVersion,
+    SyncPackwiz,
+    InstallMrpack,
+    ReadFeriumConfig,
+    SwitchFeriumProfile,
+    BackupMods,
+    UpdateMods,
+    RollBackMods,
+    RestoreFeriumProfile,
+    SelectJava,
+    CheckMods,
+    InstallGame,
+    /// The launcher's name, if known.
+    Launch(Option<&'static str>),
+}
+
+impl Step {
+    /// The steps expected to happen after the config is loaded.
+    fn plan(config: &Config, world: &str, get_state: bool, wait: bool) -> Vec<Self> {
+        let mut steps = Vec::default();
+        if get_state && config.ferium.version_override.is_none() {
+            steps.push(Self::GetServerVersion);
+        }
+        if config.packwiz.contains_key(world) {
+            steps.push(Self::SyncPackwiz);
+        } else if config.mrpack.contains_key(world) {
+            steps.push(Self::InstallMrpack);
+        } else if config.ferium.profiles.contains_key(world) {
+            steps.extend([Self::ReadFeriumConfig, Self::SwitchFeriumProfile, Self::BackupMods, Self::UpdateMods, Self::RestoreFeriumProfile]);
+        }
+        if config.java.path.is_some() || config.java.auto_select {
+            steps.push(Self::SelectJava);
+        }
+        if config.required_mods.contains_key(world) {
+            steps.push(Self::CheckMods);
+        }
+        if config.native_launcher.is_some() {
+            steps.push(Self::InstallGame);
+        }
+        if wait {
+            steps.push(Self::Launch(None));
+        }
+        steps
+    }
+
+    /// Whether the steps are the same apart from details that may not be known in advance.
+    fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoadConfig => write!(f, "loading config"),
+            Self::GetServerVersion => write!(f, "getting server version"),
+            Self::SyncPackwiz => write!(f, "updating mods from packwiz pack"),
+            Self::InstallMrpack => write!(f, "installing Modrinth modpack"),
+            Self::ReadFeriumConfig => write!(f, "reading Ferium config"),
+            Self::SwitchFeriumProfile => write!(f, "switching Ferium profiles"),
+            Self::BackupMods => write!(f, "backing up mods"),
+            Self::UpdateMods => write!(f, "updating mods"),
+            Self::RollBackMods => write!(f, "restoring previous mods"),
+            Self::RestoreFeriumProfile => write!(f, "restoring active Ferium profile"),
+            Self::SelectJava => write!(f, "selecting Java"),
+            Self::CheckMods => write!(f, "checking installed mods"),
+            Self::InstallGame => write!(f, "downloading Minecraft"),
+            Self::Launch(Some(launcher)) => write!(f, "launching Minecraft via {launcher}"),
+            Self::Launch(None) => write!(f, "launching Minecraft"),
+        }
+    }
+}
+
+/// The number of lines kept in a launch window's log panel. Older lines are discarded so a chatty game doesn't use up memory.
+const MAX_LOG_LINES: usize = 5000;
+/// The number of progress updates within a step that can be waiting for the GUI. Further updates are dropped until it catches up.
+const FRACTION_CHANNEL_CAPACITY: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Progress {
+    steps: Vec<Step>,
+    /// Index into `steps`. Steps before this one are completed.
+    current: Option<usize>,
+    /// Progress within the current step, from 0 to 1, if known.
+    fraction: Option<f32>,
+}
+
+impl Progress {
+    /// Overall progress from 0 to 1.
+    fn overall(&self) -> f32 {
+        if self.steps.is_empty() { return 0.0 }
+        let completed = self.current.map_or(0.0, |current| current as f32 + self.fraction.unwrap_or_default());
+        completed / self.steps.len() as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LogKind {
+    Command,
+    Output(command::Stream),
+}
+
+/// A line in the launch window's log panel.
+#[derive(Debug, Clone)]
+pub(crate) struct LogLine {
+    time: DateTime<Local>,
+    kind: LogKind,
+    text: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.kind {
+            LogKind::Command => "$",
+            LogKind::Output(command::Stream::Stdout) => " ",
+            LogKind::Output(command::Stream::Stderr) => "!",
+        };
+        write!(f, "{} {prefix} {}", self.time.format("%Y-%m-%d %H:%M:%S"), self.text)
+    }
+}
+
+/// Writes the log to a new file in the `logs` subfolder of the data folder.
+async fn save_log(lines: VecDeque<LogLine>) -> Result<PathBuf, LaunchError> {
+    let dir = crate::config::data_dir()?.join("logs");
+    wheel::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("launch-{}.log", Local::now().format("%Y-%m-%d-%H%M%S")));
+    wheel::fs::write(&path, lines.iter().map(|line| format!("{line}\n")).collect::<String>()).await?;
+    Ok(path)
+}
+
+/// Sends the launch task's progress to its window and checks whether the launch has been cancelled.
+struct Reporter {
+    window: window::Id,
+    tx: mpsc::UnboundedSender<Message>,
+    fraction_tx: mpsc::Sender<Message>,
+    cancel: watch::Receiver<bool>,
+    progress: Progress,
+    /// Shown as Discord Rich Presence while waiting for the game.
+    presence: Option<(config::Discord, discord::Activity)>,
+}
+
+impl Reporter {
+    fn new(window: window::Id, tx: mpsc::UnboundedSender<Message>, fraction_tx: mpsc::Sender<Message>, cancel: watch::Receiver<bool>) -> Self {
+        Self { progress: Progress::default(), presence: None, window, tx, fraction_tx, cancel }
+    }
+
+    /// Adds steps that are expected to happen after the current one.
+    fn plan(&mut self, steps: Vec<Step>) {
+        let next = self.progress.current.map_or(0, |current| current + 1);
+        self.progress.steps.splice(next.., steps);
+    }
+
+    /// Marks the given step as started and all previous steps as completed, unless the launch has been cancelled.
+    ///
+    /// If the step wasn't planned, it's inserted after the current step.
+    fn step(&mut self, step: Step) -> Result<(), LaunchError> {
+        self.check_cancelled()?;
+        self.cleanup_step(step);
+        Ok(())
+    }
+
+    fn check_cancelled(&self) -> Result<(), LaunchError> {
+        if *self.cancel.borrow() { Err(LaunchError::Cancelled) } else { Ok(()) }
+    }
+
+    /// Like [`Reporter::step`], but for steps that undo previous ones and therefore also run if the launch has been cancelled.
+    fn cleanup_step(&mut self, step: Step) {
+        let next = self.progress.current.map_or(0, |current| current + 1);
+        let index = if let Some(offset) = self.progress.steps[next..].iter().position(|planned| planned.same_kind(&step)) {
+            self.progress.steps[next + offset] = step;
+            next + offset
+        } else {
+            self.progress.steps.insert(next, step);
+            next
+        };
+        log::info!("launch step: {step}");
+        self.progress.current = Some(index);
+        self.progress.fraction = None;
+        self.tx.send(Message::Progress(self.window, self.progress.clone())).allow_unreceived();
+    }
+
+    /// Resolves when the launch is cancelled, or when the window is gone.
+    fn cancellation(&self) -> impl Future<Output = ()> + use<> {
+        let mut cancel = self.cancel.clone();
+        async move {
+            let _ = cancel.wait_for(|&cancelled| cancelled).await;
+        }
+    }
+
+    /// Runs the future to completion unless the launch is cancelled first, in which case the future is dropped.
+    ///
+    /// Futures that write files shouldn't be dropped midway, so they take the cancellation flag and stop between files instead, see [`crate::download::check_cancelled`].
+    async fn until_cancelled<T>(&self, future: impl Future<Output = T>) -> Result<T, LaunchError> {
+        tokio::select! {
+            output = future => Ok(output),
+            () = self.cancellation() => Err(LaunchError::Cancelled),
+        }
+    }
+
+    /// Reports progress within the current step. Dropped if the GUI hasn't caught up with previous reports.
+    fn fraction(&self, done: usize, total: usize) {
+        if total > 0 && let Some(step) = self.progress.current {
+            let _ = self.fraction_tx.try_send(Message::Fraction(self.window, step, done as f32 / total as f32));
+        }
+    }
+
+    /// Spawns the command after adding it to the log panel. If `wait` is true, its output is piped so [`Reporter::wait`] can add it to the log panel.
+    fn spawn(&self, cmd: &mut Command, wait: bool) -> io::Result<Child> {
+        cmd.kill_on_drop(wait);
+        if wait {
+            command::pipe(cmd);
+        }
+        self.log_command(cmd);
+        cmd.spawn()
+    }
+
+    /// Waits for a child process spawned by [`Reporter::spawn`] to exit successfully unless the launch is cancelled first, adding its output to the log panel.
+    async fn wait(&self, child: Child, name: &'static str) -> Result<(), LaunchError> {
+        let presence = if let Some((ref config, ref activity)) = self.presence {
+            discord::Presence::show(config, activity).await
+        } else {
+            None
+        };
+        let result = self.until_cancelled(command::wait(child, name, |stream, line| self.log_output(stream, line))).await;
+        if let Some(presence) = presence {
+            presence.clear().await;
+        }
+        result??;
+        Ok(())
+    }
+
+    /// Shows Discord Rich Presence (if configured) until a child process spawned by [`Reporter::spawn`] without waiting exits.
+    fn detach(&self, mut child: Child) {
+        if let Some((config, activity)) = self.presence.clone() {
+            tokio::spawn(async move {
+                if let Some(presence) = discord::Presence::show(&config, &activity).await {
+                    let _ = child.wait().await;
+                    presence.clear().await;
+                }
+            });
+        }
+    }
+
+    /// Adds the command line to the log panel. Should be called right before the command is spawned.
+    fn log_command(&self, cmd: &Command) {
+        self.log(LogKind::Command, command::display(cmd));
+    }
+
+    /// Adds a line of a child process's output to the log panel.
+    fn log_output(&self, stream: command::Stream, line: &str) {
+        self.log(LogKind::Output(stream), line.to_owned());
+    }
+
+    fn log(&self, kind: LogKind, text: String) {
+        match kind {
+            LogKind::Command => log::info!("running `{text}`"),
+            LogKind::Output(command::Stream::Stdout) => log::debug!("stdout: {text}"),
+            LogKind::Output(command::Stream::Stderr) => log::debug!("stderr: {text}"),
+        }
+        self.tx.send(Message::Log(self.window, LogLine { time: Local::now(), kind, text })).allow_unreceived();
+    }
+}
+
 #[derive(Debug, thiserror::Error, wheel::FromArc)]
-enum LaunchError {
+pub(crate) enum LaunchError {
+    #[error(transparent)] Auth(#[from] auth::Error),
+    #[error(transparent)] Command(#[from] command::Error),
     #[error(transparent)] Config(#[from] crate::config::Error),
+    #[error(transparent)] Ferium(#[from] ferium::Error),
+    #[error(transparent)] Java(#[from] java::Error),
+    #[error(transparent)] Launcher(#[from] launcher::Error),
+    #[error(transparent)] Loader(#[from] loader::Error),
+    #[error(transparent)] Mods(#[from] mods::Error),
+    #[error(transparent)] Mrpack(#[from] mrpack::Error),
+    #[error(transparent)] Native(#[from] native::Error),
+    #[error(transparent)] Packwiz(#[from] packwiz::Error),
     #[error(transparent)] Reqwest(#[from] reqwest::Error),
     #[error(transparent)] #[from_arc] State(#[from] Arc<crate::Error>),
-    #[error(transparent)] Utf8(#[from] std::string::FromUtf8Error),
     #[error(transparent)] Wheel(#[from] wheel::Error),
-    #[error("failed to parse `ferium profile` command output")]
-    FeriumProfileFormat,
+    #[error("launch cancelled")]
+    Cancelled,
+    #[error("failed to update mods, the previous mods have been restored: {0}")]
+    FeriumUpgrade(#[source] command::Error),
+    #[error("failed to update mods ({upgrade}), and restoring the previous mods also failed, so the mods folder may be inconsistent{}: {rollback}", snapshot_dir.as_ref().map(|snapshot_dir| format!(" (the previous mods are in {})", snapshot_dir.display())).unwrap_or_default())]
+    FeriumRollback {
+        upgrade: Box<LaunchError>,
+        #[source]
+        rollback: ferium::Error,
+        snapshot_dir: Option<PathBuf>,
+    },
+    #[error("no folder to install the Modrinth modpack into, please configure `instanceDir` or `prismInstance`")]
+    MrpackInstanceDir,
+    #[error("not signed in to Minecraft, please sign in using the tray menu or the `sign-in` subcommand")]
+    NoAccount,
+}
+
+impl LaunchError {
+    /// The standard error output of the command whose failure caused this error, if any.
+    fn stderr(&self) -> Option<String> {
+        match self {
+            Self::Command(command::Error::Exit { output, .. }) | Self::FeriumUpgrade(command::Error::Exit { output, .. }) => Some(String::from_utf8_lossy(&output.stderr).into_owned()),
+            Self::Wheel(wheel::Error::CommandExit { output, .. }) => Some(String::from_utf8_lossy(&output.stderr).into_owned()),
+            Self::FeriumRollback { upgrade, .. } => upgrade.stderr(),
+            _ => None,
+        }
+    }
 }
 
-async fn launch_minecraft(config: Option<Config>, http_client: &reqwest::Client, state: Option<Result<State, Arc<crate::Error>>>, menu: bool, wait: bool, window: window::Id, tx: mpsc::Sender<Message>) -> Result<(), LaunchError> {
+async fn launch_minecraft(config: Option<Config>, http_client: &reqwest::Client, state: Option<Result<State, Arc<crate::Error>>>, world: String, menu: bool, wait: bool, window: window::Id, tx: mpsc::UnboundedSender<Message>, fraction_tx: mpsc::Sender<Message>, decisions: &mut mpsc::Receiver<bool>, cancel: watch::Receiver<bool>) -> Result<(), LaunchError> {
+    log::info!("launching Minecraft (world: {world}, menu: {menu}, wait: {wait})");
+    let mut reporter = Reporter::new(window, tx.clone(), fraction_tx, cancel);
     let config = if let Some(config) = config {
         config
     } else {
-        tx.send(Message::Progress(window, "loading config")).await.allow_unreceived();
+        reporter.step(Step::LoadConfig)?;
         Config::load().await?
     };
-    let game_version = if let Some(ref version_override) = config.ferium.version_override {
-        Some(version_override.clone())
+    reporter.plan(Step::plan(&config, &world, state.is_none(), wait));
+    let world_status = if let Some(state) = state {
+        let (_, mut world_statuses) = state?;
+        world_statuses.remove(&world)
+    } else if config.ferium.version_override.is_none() {
+        reporter.step(Step::GetServerVersion)?;
+        let (_, mut world_statuses) = crate::get_state(http_client).await?;
+        world_statuses.remove(&world)
     } else {
-        let (_, world_status) = if let Some(state) = state {
-            state?
+        None
+    };
+    reporter.presence = config.discord.clone().map(|discord| (discord, discord::Activity::new(&world, world_status.as_ref())));
+    let mut game_version = config.ferium.version_override.clone().or_else(|| world_status.as_ref().and_then(|world_status| world_status.version.clone()));
+    let server_address = if menu {
+        None
+    } else {
+        let server_address = crate::server_address(&config, &world, world_status.as_ref());
+        if server_address.is_none() {
+            log::warn!("no server address known for world {world}, launching into the main menu");
+        }
+        server_address
+    };
+    let mut modpack_loader = None;
+    let modpack_dir = if let Some(pack) = config.packwiz.get(&world) {
+        reporter.step(Step::SyncPackwiz)?;
+        let result = packwiz::sync(http_client, &pack.pack, &pack.instance_dir, &reporter.cancel, |done, total| reporter.fraction(done, total)).await;
+        reporter.check_cancelled()?;
+        let mut versions = result?;
+        if let Some(minecraft_version) = versions.remove("minecraft") {
+            game_version = Some(minecraft_version);
+        }
+        modpack_loader = Loader::from_packwiz(&mut versions);
+        Some(pack.instance_dir.clone())
+    } else if let Some(pack) = config.mrpack.get(&world) {
+        let instance_dir = if let Some(ref instance_dir) = pack.instance_dir {
+            instance_dir.clone()
         } else {
-            tx.send(Message::Progress(window, "getting server version")).await.allow_unreceived();
-            crate::get_state(http_client).await?
+            launcher::SearchRoots::new()?.prism_instance_dir(&config).await?.ok_or(LaunchError::MrpackInstanceDir)?
         };
-        world_status.get(MAIN_WORLD).and_then(|world_status| world_status.version.clone())
-    };
-    let portablemc_work_dir = if let Some(ferium_profile) = config.ferium.profiles.get(MAIN_WORLD) {
+        reporter.step(Step::InstallMrpack)?;
+        let result = mrpack::install(http_client, &pack.pack, &instance_dir, &reporter.cancel, |done, total| reporter.fraction(done, total)).await;
+        reporter.check_cancelled()?;
+        let mut dependencies = result?;
+        if let Some(minecraft_version) = dependencies.remove("minecraft") {
+            game_version = Some(minecraft_version);
+        }
+        modpack_loader = Loader::from_mrpack(&mut dependencies);
+        Some(instance_dir)
+    } else if let Some(ferium_profile) = config.ferium.profiles.get(&world) {
         if let Some(ref game_version) = game_version {
-            tx.send(Message::Progress(window, "checking active Ferium profile")).await.allow_unreceived();
-            let previous_profile = config.ferium.command()
-                .arg("profile")
-                .release_create_no_window()
-                .check("ferium profile").await?
-                .stdout;
-            let mut previous_profile = String::from_utf8(previous_profile)?;
-            previous_profile.truncate(previous_profile.find(" *").ok_or(LaunchError::FeriumProfileFormat)?);
-            tx.send(Message::Progress(window, "switching Ferium profiles")).await.allow_unreceived();
-            config.ferium.command()
-                .arg("profile")
-                .arg("switch")
-                .arg(ferium_profile)
-                .release_create_no_window()
-                .kill_on_drop(true)
-                .check("ferium profile switch").await?;
-            tx.send(Message::Progress(window, "getting Ferium profile data")).await.allow_unreceived();
-            let current_profile = config.ferium.command()
-                .arg("profile")
-                .release_create_no_window()
-                .kill_on_drop(true)
-                .check("ferium profile").await?
-                .stdout;
-            tx.send(Message::Progress(window, "setting game version")).await.allow_unreceived();
-            config.ferium.command()
-                .arg("profile")
-                .arg("configure")
-                .arg("--game-version")
-                .arg(game_version)
-                .release_create_no_window()
-                .kill_on_drop(true)
-                .check("ferium profile configure --game-version").await?;
-            tx.send(Message::Progress(window, "updating mods")).await.allow_unreceived();
-            config.ferium.command()
-                .arg("upgrade")
-                .release_create_no_window()
-                .kill_on_drop(true)
-                .check("ferium upgrade").await?;
-            tx.send(Message::Progress(window, "restoring active Ferium profile")).await.allow_unreceived();
-            config.ferium.command()
-                .arg("profile")
-                .arg("switch")
-                .arg(previous_profile)
-                .release_create_no_window()
-                .kill_on_drop(true)
-                .check("ferium profile switch").await?;
-            current_profile.lines().find_map(|line| line.ok().and_then(|line| line.strip_prefix("        \r  Output directory:   ").map(|dir| {
-                let mut dir = PathBuf::from(dir);
-                dir.pop();
-                dir
-            })))
+            reporter.step(Step::ReadFeriumConfig)?;
+            let ferium_config_path = ferium::config_path(&config.ferium)?;
+            let mut ferium_data = ferium::Data::load(&ferium_config_path).await?;
+            let profile_index = ferium_data.profile_index(ferium_profile)?;
+            let profile = &mut ferium_data.profiles[profile_index];
+            if profile.game_version() != Some(game_version.as_str()) {
+                profile.set_game_version(game_version);
+            }
+            let game_dir = profile.game_dir();
+            let mods_dir = profile.output_dir.clone();
+            reporter.step(Step::SwitchFeriumProfile)?;
+            let profile_switch = ferium::ProfileSwitch::new(ferium_config_path, &mut ferium_data, profile_index).await?;
+            reporter.step(Step::BackupMods)?;
+            let snapshot = ferium::ModsSnapshot::take(mods_dir).await?;
+            reporter.step(Step::UpdateMods)?;
+            let mut cmd = config.ferium.command();
+            cmd.arg("upgrade");
+            cmd.release_create_no_window();
+            cmd.kill_on_drop(true);
+            reporter.log_command(&cmd);
+            // ferium's output isn't a stable interface, so the progress of this step is shown as unknown
+            let upgrade_error = match reporter.until_cancelled(command::run(&mut cmd, "ferium upgrade", |stream, line| reporter.log_output(stream, line))).await {
+                Ok(Ok(_)) => None,
+                Ok(Err(e)) => Some(LaunchError::FeriumUpgrade(e)),
+                // dropping the `ferium upgrade` future has killed the process
+                Err(e) => Some(e),
+            };
+            if let Some(e) = upgrade_error {
+                // failing to restore the profile is only logged so the reason the upgrade failed isn't lost
+                reporter.cleanup_step(Step::RollBackMods);
+                let snapshot_dir = snapshot.snapshot_dir().map(Path::to_owned);
+                let rollback_result = snapshot.rollback().await;
+                reporter.cleanup_step(Step::RestoreFeriumProfile);
+                if let Err(restore_error) = profile_switch.restore().await {
+                    log::error!("failed to restore ferium profile after failed upgrade: {restore_error} ({restore_error:?})");
+                }
+                return Err(match rollback_result {
+                    Ok(()) => e,
+                    Err(rollback) => LaunchError::FeriumRollback {
+                        // the message of `FeriumUpgrade` says the mods have been restored
+                        upgrade: Box::new(if let LaunchError::FeriumUpgrade(e) = e { LaunchError::Command(e) } else { e }),
+                        rollback, snapshot_dir,
+                    },
+                })
+            }
+            snapshot.discard().await?;
+            reporter.cleanup_step(Step::RestoreFeriumProfile);
+            profile_switch.restore().await?;
+            Some(game_dir)
         } else {
             None
         }
     } else {
         None
     };
-    if let Some(ref portablemc_uuid) = config.portablemc.uuid {
+    let (loader, loader_version) = if let Some(mod_loader) = config.mod_loaders.get(&world) {
+        let modpack_version = modpack_loader.filter(|(loader, _)| *loader == mod_loader.loader).map(|(_, version)| version);
+        (mod_loader.loader, mod_loader.version.clone().or(modpack_version))
+    } else if let Some((loader, version)) = modpack_loader {
+        (loader, Some(version))
+    } else {
+        // Fabric is what most Wurstmineberg worlds have used so far
+        (world_status.as_ref().and_then(|world_status| world_status.loader).unwrap_or(Loader::Fabric), None)
+    };
+    log::info!("using {loader} {}", loader_version.as_deref().unwrap_or("(latest)"));
+    let version_spec = loader.portablemc_version(game_version.as_deref(), loader_version.as_deref());
+    let join_method = quick_play::JoinMethod::for_version(game_version.as_deref());
+    if let Some(ref server_address) = server_address {
+        log::info!("joining {server_address} using {join_method}");
+    }
+    let java_path = if config.java.path.is_some() || config.java.auto_select {
+        reporter.step(Step::SelectJava)?;
+        let java_path = java::select(&config.java, java::required_version(game_version.as_deref())).await?;
+        match java_path {
+            Some(ref java_path) => log::info!("using Java at {}", java_path.display()),
+            None => log::warn!("no suitable Java found, leaving the choice to the launcher"),
+        }
+        java_path
+    } else {
+        None
+    };
+    let jvm_args = java::jvm_args(&config.java);
+    let search_roots = launcher::SearchRoots::new()?;
+    let game_dir = if let Some(game_dir) = config.native_launcher.as_ref().and_then(|native_launcher| native_launcher.game_dir.clone()) {
+        Some(game_dir)
+    } else if let Some(modpack_dir) = modpack_dir {
+        Some(modpack_dir)
+    } else if config.native_launcher.is_none() && config.portablemc.uuid.is_none() && config.portablemc.email.is_none() && let Some(instance_dir) = search_roots.prism_instance_dir(&config).await? {
+        Some(instance_dir)
+    } else {
+        search_roots.minecraft_dir(&config).await?
+    };
+    if let Some(required_mods) = config.required_mods.get(&world) && let Some(ref game_dir) = game_dir {
+        reporter.step(Step::CheckMods)?;
+        let report = mods::Report::new(mods::installed(game_dir).await?, required_mods);
+        if !report.is_compatible() {
+            tx.send(Message::ModReport(window, report)).allow_unreceived();
+            if !reporter.until_cancelled(decisions.recv()).await?.unwrap_or(false) {
+                return Err(LaunchError::Cancelled)
+            }
+        }
+    }
+    if let Some(ref native_launcher) = config.native_launcher {
+        let account = auth::account(http_client, &config.sign_in).await?.ok_or(LaunchError::NoAccount)?;
+        reporter.step(Step::InstallGame)?;
+        let result = native::install(http_client, native_launcher, game_version.as_deref(), loader, loader_version.as_deref(), &reporter.cancel, |done, total| reporter.fraction(done, total)).await;
+        reporter.check_cancelled()?;
+        let installation = result?;
+        let game_dir = if let Some(game_dir) = game_dir { game_dir } else { crate::config::data_dir()?.join("minecraft") };
+        wheel::fs::create_dir_all(&game_dir).await?;
+        let mut cmd = installation.command(java_path.as_deref(), &jvm_args, &account, &game_dir, server_address.as_deref());
+        cmd.release_create_no_window();
+        let child = reporter.spawn(&mut cmd, wait).at_command("java")?;
+        if wait {
+            reporter.step(Step::Launch(Some("built-in launcher")))?;
+            reporter.wait(child, "java").await?;
+        } else {
+            reporter.detach(child);
+        }
+    } else if let Some(ref portablemc_uuid) = config.portablemc.uuid {
         let mut cmd = Command::new("portablemc");
         cmd.arg("start");
-        if let Some(work_dir) = portablemc_work_dir {
+        if let Some(game_dir) = game_dir {
             cmd.arg("--mc-dir");
-            cmd.arg(work_dir);
+            cmd.arg(game_dir);
         }
         cmd.arg("--auth");
         cmd.arg("--uuid");
         cmd.arg(portablemc_uuid.to_string());
-        if !menu {
-            cmd.arg("--join-server=wurstmineberg.de");
+        if let Some(ref server_address) = server_address {
+            cmd.args(join_method.portablemc_args(server_address));
+        }
+        if let Some(ref java_path) = java_path {
+            cmd.arg("--jvm");
+            cmd.arg(java_path);
+        }
+        if !jvm_args.is_empty() {
+            cmd.arg(format!("--jvm-args={}", jvm_args.join(" ")));
         }
-        cmd.arg(format!("fabric:{}", game_version.unwrap_or_default()));
+        cmd.arg(&version_spec);
         cmd.release_create_no_window();
-        cmd.kill_on_drop(wait);
-        let child = cmd.spawn().at_command("portablemc")?;
+        let child = reporter.spawn(&mut cmd, wait).at_command("portablemc")?;
         if wait {
-            tx.send(Message::Progress(window, "launching Minecraft via new portablemc")).await.allow_unreceived();
-            child.check("portablemc").await?;
+            reporter.step(Step::Launch(Some("new portablemc")))?;
+            reporter.wait(child, "portablemc").await?;
+        } else {
+            reporter.detach(child);
         }
     } else if let Some(ref portablemc_email) = config.portablemc.email {
         let mut cmd = Command::new("python");
         cmd.arg("-m");
         cmd.arg("portablemc");
-        if let Some(work_dir) = portablemc_work_dir {
+        if let Some(game_dir) = game_dir {
             cmd.arg("--work-dir");
-            cmd.arg(work_dir);
+            cmd.arg(game_dir);
         }
         cmd.arg("start");
-        cmd.arg(format!("fabric:{}", game_version.unwrap_or_default()));
-        if !menu {
-            cmd.arg("--server=wurstmineberg.de");
+        cmd.arg(&version_spec);
+        if let Some(ref server_address) = server_address {
+            cmd.arg(format!("--server={server_address}"));
+        }
+        if let Some(ref java_path) = java_path {
+            cmd.arg("--jvm");
+            cmd.arg(java_path);
+        }
+        if !jvm_args.is_empty() {
+            cmd.arg(format!("--jvm-args={}", jvm_args.join(" ")));
         }
         cmd.arg("--login");
         cmd.arg(portablemc_email);
         cmd.release_create_no_window();
-        cmd.kill_on_drop(wait);
-        let child = cmd.spawn().at_command("python -m portablemc")?;
+        let child = reporter.spawn(&mut cmd, wait).at_command("python -m portablemc")?;
         if wait {
-            tx.send(Message::Progress(window, "launching Minecraft via old portablemc")).await.allow_unreceived();
-            child.check("python -m portablemc").await?;
+            reporter.step(Step::Launch(Some("old portablemc")))?;
+            reporter.wait(child, "python -m portablemc").await?;
+        } else {
+            reporter.detach(child);
         }
     } else {
+        if let Some(instance_dir) = search_roots.prism_instance(&config).await? {
+            if let Some(ref game_version) = game_version {
+                loader::set_prism_components(&instance_dir, game_version, loader, loader_version.as_deref()).await?;
+            }
+            java::configure_prism(&instance_dir, java_path.as_deref(), &config.java).await?;
+            launcher::write_prism_settings(&instance_dir, quick_play::prism_settings(server_address.as_deref().map(|server_address| (join_method, server_address)))).await?;
+        }
         let mut prism_command = Command::new("prismlauncher");
         if let Some(ref instance) = config.prism_instance {
-            prism_command.arg("--show");
-            prism_command.arg(instance);
+            if let Some(ref server_address) = server_address {
+                prism_command.args(join_method.prism_args(instance, server_address));
+            } else {
+                prism_command.arg("--show");
+                prism_command.arg(instance);
+            }
         }
-        match prism_command.release_create_no_window().kill_on_drop(wait).spawn() {
+        match reporter.spawn(prism_command.release_create_no_window(), wait) {
             Ok(child) => if wait {
-                tx.send(Message::Progress(window, "launching Minecraft via Prism")).await.allow_unreceived();
-                child.check("prismlauncher").await?;
+                reporter.step(Step::Launch(Some("Prism")))?;
+                reporter.wait(child, "prismlauncher").await?;
+            } else {
+                reporter.detach(child);
             },
-            Err(e) if e.kind() == io::ErrorKind::NotFound => match Command::new("C:\\Program Files (x86)\\Minecraft Launcher\\MinecraftLauncher.exe")
-                .release_create_no_window()
-                .kill_on_drop(wait)
-                .spawn()
-            {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match reporter.spawn(Command::new("C:\\Program Files (x86)\\Minecraft Launcher\\MinecraftLauncher.exe").release_create_no_window(), wait) {
                 Ok(child) => if wait {
-                    tx.send(Message::Progress(window, "launching Minecraft via old launcher")).await.allow_unreceived();
-                    child.check("C:\\Program Files (x86)\\Minecraft Launcher\\MinecraftLauncher.exe").await?;
+                    reporter.step(Step::Launch(Some("old launcher")))?;
+                    reporter.wait(child, "C:\\Program Files (x86)\\Minecraft Launcher\\MinecraftLauncher.exe").await?;
+                } else {
+                    reporter.detach(child);
                 },
                 Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    let child = Command::new("explorer")
-                        .arg("shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft")
-                        .release_create_no_window()
-                        .kill_on_drop(wait)
-                        .spawn().at_command("explorer shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft")?;
+                    let child = reporter.spawn(
+                        Command::new("explorer")
+                            .arg("shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft")
+                            .release_create_no_window(),
+                        wait,
+                    ).at_command("explorer shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft")?;
                     if wait {
-                        tx.send(Message::Progress(window, "launching Minecraft via new launcher")).await.allow_unreceived();
-                        child.check("explorer shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft").await?;
+                        reporter.step(Step::Launch(Some("new launcher")))?;
+                        reporter.wait(child, "explorer shell:AppsFolder\\Microsoft.4297127D64EC6_8wekyb3d8bbwe!Minecraft").await?;
                     }
                 }
                 Err(e) => return Err(e).at_command("C:\\Program Files (x86)\\Minecraft Launcher\\MinecraftLauncher.exe").map_err(LaunchError::from),
@@ -212,7 +675,7 @@ async fn launch_minecraft(config: Option<Config>, http_client: &reqwest::Client,
             Err(e) => return Err(e).at_command("prismlauncher").map_err(LaunchError::from),
         }
     }
-    tx.send(Message::LaunchDone(window)).await.allow_unreceived();
+    tx.send(Message::LaunchDone(window)).allow_unreceived();
     Ok(())
 }
 
@@ -223,91 +686,448 @@ pub(crate) enum Error {
 
 #[derive(Debug, Clone)]
 pub(crate) enum Message {
+    Cancel(window::Id),
     CloseRequested(window::Id),
     CommandError(Arc<Error>),
     Exit,
+    /// Progress within the step with the given index.
+    Fraction(window::Id, usize, f32),
     HandleLauncherWindow {
         config: Option<Config>,
         state: Option<Result<State, Arc<crate::Error>>>,
+        world: String,
         menu: bool,
         wait: bool,
         window: window::Id,
     },
+    CopyErrorDetails(window::Id),
+    CopyUserCode(window::Id),
     LaunchDone(window::Id),
+    LaunchFailed(window::Id, Arc<LaunchError>),
     LaunchMinecraft {
         config: Option<Config>,
         state: Option<Result<State, Arc<crate::Error>>>,
+        /// The Wurstmineberg world whose version and mods to use and which to join unless `menu` is true.
+        world: String,
         menu: bool,
         wait: bool,
     },
-    Progress(window::Id, &'static str),
+    HandleSignInWindow {
+        config: Option<Config>,
+        window: window::Id,
+    },
+    Log(window::Id, LogLine),
+    LogSaved(window::Id, Result<PathBuf, Arc<LaunchError>>),
+    ModReport(window::Id, mods::Report),
+    ModReportDecision(window::Id, bool),
+    Progress(window::Id, Progress),
+    Retry(window::Id),
+    OpenVerificationUri(window::Id),
+    SaveLog(window::Id),
+    SignIn {
+        config: Option<Config>,
+    },
+    SignInCode(window::Id, auth::DeviceCode),
+    SignInDone(window::Id, Result<String, Arc<auth::Error>>),
+    ToggleErrorDetails(window::Id),
+    ToggleLog(window::Id),
+}
+
+/// The parameters of a launch, kept so it can be retried.
+#[derive(Clone)]
+struct LaunchArgs {
+    config: Option<Config>,
+    state: Option<Result<State, Arc<crate::Error>>>,
+    world: String,
+    menu: bool,
+    wait: bool,
+}
+
+#[derive(Default)]
+struct LaunchWindow {
+    progress: Progress,
+    task: Option<JoinHandle<()>>,
+    /// Tells the launch task whether to continue despite the mod report.
+    decisions: Option<mpsc::Sender<bool>>,
+    cancel: Option<watch::Sender<bool>>,
+    cancelling: bool,
+    mod_report: Option<mods::Report>,
+    args: Option<LaunchArgs>,
+    error: Option<Arc<LaunchError>>,
+    show_error_details: bool,
+    log: VecDeque<LogLine>,
+    show_log: bool,
+    /// The result of the most recent click on “Save log”.
+    log_saved: Option<Result<PathBuf, Arc<LaunchError>>>,
+}
+
+impl LaunchWindow {
+    /// The step that was in progress when the launch failed, if known.
+    fn failed_step(&self) -> Option<Step> {
+        self.progress.current.map(|current| self.progress.steps[current])
+    }
+
+    fn error_details(&self) -> Option<String> {
+        let error = self.error.as_ref()?;
+        let mut details = format!("{error}\n");
+        if let Some(step) = self.failed_step() {
+            details.push_str(&format!("Failed step: {step}\n"));
+        }
+        details.push_str(&format!("Debug info: {error:?}\n"));
+        if let Some(stderr) = error.stderr() {
+            details.push_str(&format!("\nstderr:\n{stderr}"));
+        }
+        Some(details)
+    }
+
+    fn log_view(&self, window: window::Id) -> Column<'_, Message> {
+        let mut col = Column::new()
+            .push(Row::new()
+                .push(Button::new(if self.show_log { "Hide log" } else { "Show log" }).on_press(Message::ToggleLog(window)))
+                .push(Button::new("Save log").on_press(Message::SaveLog(window)))
+                .push(match self.log_saved {
+                    None => Text::new(""),
+                    Some(Ok(ref path)) => Text::new(format!("saved to {}", path.display())),
+                    Some(Err(ref e)) => Text::new(format!("failed to save log: {e}")),
+                })
+                .spacing(8)
+            )
+            .spacing(8);
+        if self.show_log {
+            let mut lines = Column::new();
+            for line in &self.log {
+                lines = lines.push(Text::new(line.to_string()).font(iced::Font::MONOSPACE).size(12));
+            }
+            col = col.push(Scrollable::new(lines).anchor_bottom().height(Length::Fill));
+        }
+        col
+    }
+}
+
+#[derive(Default)]
+struct SignInWindow {
+    task: Option<JoinHandle<()>>,
+    code: Option<auth::DeviceCode>,
+    /// The player name if signing in succeeded.
+    result: Option<Result<String, Arc<auth::Error>>>,
+}
+
+async fn sign_in(config: Option<Config>, http_client: &reqwest::Client, window: window::Id, tx: &mpsc::UnboundedSender<Message>) -> Result<String, auth::Error> {
+    let config = if let Some(config) = config { config } else { Config::load().await? };
+    let account = auth::sign_in(http_client, &config.sign_in, |code| tx.send(Message::SignInCode(window, code)).allow_unreceived()).await?;
+    Ok(account.name)
 }
 
 struct Gui {
     http_client: reqwest::Client,
     exit_on_close: bool,
-    progress: HashMap<window::Id, &'static str>,
-    task: Option<JoinHandle<()>>,
+    windows: HashMap<window::Id, LaunchWindow>,
+    sign_in_windows: HashMap<window::Id, SignInWindow>,
 }
 
 impl Gui {
     fn new(http_client: reqwest::Client, exit_on_close: bool) -> Self {
         Self {
-            progress: HashMap::default(),
-            task: None,
+            windows: HashMap::default(),
+            sign_in_windows: HashMap::default(),
             http_client, exit_on_close,
         }
     }
 
-    fn title(&self, _: window::Id) -> String {
-        format!("Launching Minecraft — Wurstmineberg")
+    fn title(&self, window: window::Id) -> String {
+        if self.sign_in_windows.contains_key(&window) {
+            format!("Sign in to Minecraft — Wurstmineberg")
+        } else {
+            format!("Launching Minecraft — Wurstmineberg")
+        }
+    }
+
+    fn close(&self, window: window::Id) -> Task<Message> {
+        if self.exit_on_close {
+            iced::exit()
+        } else {
+            window::close(window)
+        }
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::CloseRequested(window) => {
-                if let Some(task) = self.task.take() {
-                    task.abort();
+            Message::Cancel(window) => {
+                if let Some(launch_window) = self.windows.get_mut(&window) && let Some(ref cancel) = launch_window.cancel {
+                    // the task will report `LaunchDone` once it has cleaned up
+                    cancel.send_replace(true);
+                    launch_window.cancelling = true;
                 }
-                window::close(window)
+                Task::none()
             }
-            Message::CommandError(e) => nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = gui::CommandError, {e:?}")),
+            Message::CloseRequested(window) => if let Some(sign_in_window) = self.sign_in_windows.remove(&window) {
+                if let Some(task) = sign_in_window.task {
+                    task.abort();
+                }
+                self.close(window)
+            } else if self.windows.get(&window).and_then(|launch_window| launch_window.task.as_ref()).is_some_and(|task| !task.is_finished()) {
+                Task::done(Message::Cancel(window))
+            } else {
+                self.windows.remove(&window);
+                self.close(window)
+            },
+            Message::CopyErrorDetails(window) => if let Some(details) = self.windows.get(&window).and_then(LaunchWindow::error_details) {
+                iced::clipboard::write(details)
+            } else {
+                Task::none()
+            },
+            Message::CopyUserCode(window) => if let Some(code) = self.sign_in_windows.get(&window).and_then(|sign_in_window| sign_in_window.code.as_ref()) {
+                iced::clipboard::write(code.user_code.clone())
+            } else {
+                Task::none()
+            },
+            Message::CommandError(e) => crate::fatal("gui::CommandError", e),
             Message::Exit => iced::exit(),
-            Message::HandleLauncherWindow { config, state, menu, wait, window } => {
+            Message::HandleLauncherWindow { config, state, world, menu, wait, window } => {
                 let http_client = self.http_client.clone();
-                let (tx, rx) = mpsc::channel(32);
-                self.task = Some(tokio::spawn(async move {
-                    if let Err(e) = launch_minecraft(config, &http_client, state, menu, wait, window, tx).await {
-                        nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = gui::launch_minecraft, {e:?}"))
+                let (tx, rx) = mpsc::unbounded_channel();
+                let (fraction_tx, fraction_rx) = mpsc::channel(FRACTION_CHANNEL_CAPACITY);
+                let (decisions_tx, mut decisions_rx) = mpsc::channel(1);
+                let (cancel_tx, cancel_rx) = watch::channel(false);
+                let launch_window = self.windows.entry(window).or_default();
+                launch_window.args = Some(LaunchArgs { config: config.clone(), state: state.clone(), world: world.clone(), menu, wait });
+                launch_window.decisions = Some(decisions_tx);
+                launch_window.cancel = Some(cancel_tx);
+                launch_window.task = Some(tokio::spawn(async move {
+                    match launch_minecraft(config, &http_client, state, world, menu, wait, window, tx.clone(), fraction_tx, &mut decisions_rx, cancel_rx).await {
+                        Ok(()) => log::info!("launch done"),
+                        Err(LaunchError::Cancelled) => {
+                            log::info!("launch cancelled");
+                            tx.send(Message::LaunchDone(window)).allow_unreceived();
+                        }
+                        Err(e) => {
+                            log::error!("launch failed: {e} ({e:?})");
+                            tx.send(Message::LaunchFailed(window, Arc::new(e))).allow_unreceived();
+                        }
                     }
                 }));
-                Task::stream(ReceiverStream::new(rx))
+                Task::stream(stream::select(UnboundedReceiverStream::new(rx), ReceiverStream::new(fraction_rx)))
+            }
+            Message::HandleSignInWindow { config, window } => {
+                let http_client = self.http_client.clone();
+                let (tx, rx) = mpsc::unbounded_channel();
+                self.sign_in_windows.entry(window).or_default().task = Some(tokio::spawn(async move {
+                    let result = sign_in(config, &http_client, window, &tx).await;
+                    if let Err(ref e) = result {
+                        log::error!("sign-in failed: {e} ({e:?})");
+                    }
+                    tx.send(Message::SignInDone(window, result.map_err(Arc::new))).allow_unreceived();
+                }));
+                Task::stream(UnboundedReceiverStream::new(rx))
+            }
+            Message::LaunchDone(window) => {
+                self.windows.remove(&window);
+                self.close(window)
+            }
+            Message::LaunchFailed(window, e) => {
+                self.windows.entry(window).or_default().error = Some(e);
+                Task::none()
+            }
+            Message::LaunchMinecraft { config, state, world, menu, wait } => window::open(window::Settings {
+                size: Size { width: 512.0, height: 320.0 },
+                icon: icon::from_file_data(include_bytes!("../assets/wurstpick.ico"), Some(::image::ImageFormat::Ico)).ok(),
+                exit_on_close_request: false,
+                ..window::Settings::default()
+            }).1.map(move |window| Message::HandleLauncherWindow { config: config.clone(), state: state.clone(), world: world.clone(), menu, wait, window }),
+            Message::Log(window, line) => {
+                let log = &mut self.windows.entry(window).or_default().log;
+                if log.len() >= MAX_LOG_LINES {
+                    log.pop_front();
+                }
+                log.push_back(line);
+                Task::none()
+            }
+            Message::LogSaved(window, result) => {
+                if let Some(launch_window) = self.windows.get_mut(&window) {
+                    launch_window.log_saved = Some(result);
+                }
+                Task::none()
             }
-            Message::LaunchDone(window) => if self.exit_on_close {
-                iced::exit()
+            Message::OpenVerificationUri(window) => {
+                if let Some(code) = self.sign_in_windows.get(&window).and_then(|sign_in_window| sign_in_window.code.as_ref()) && let Err(e) = open::that(&code.verification_uri) {
+                    log::error!("failed to open sign-in page: {e}");
+                }
+                Task::none()
+            }
+            Message::ModReport(window, report) => {
+                self.windows.entry(window).or_default().mod_report = Some(report);
+                window::resize(window, Size { width: 512.0, height: 384.0 })
+            }
+            Message::ModReportDecision(window, decision) => {
+                if let Some(launch_window) = self.windows.get_mut(&window) {
+                    launch_window.mod_report = None;
+                    if let Some(ref decisions) = launch_window.decisions {
+                        let _ = decisions.try_send(decision);
+                    }
+                }
+                Task::none()
+            }
+            Message::Fraction(window, step, fraction) => {
+                let progress = &mut self.windows.entry(window).or_default().progress;
+                // sent separately from step changes, so it may arrive after the next step has started
+                if progress.current == Some(step) {
+                    progress.fraction = Some(fraction);
+                }
+                Task::none()
+            }
+            Message::Progress(window, progress) => {
+                self.windows.entry(window).or_default().progress = progress;
+                Task::none()
+            }
+            Message::Retry(window) => if let Some(LaunchArgs { config, state, world, menu, wait }) = self.windows.remove(&window).and_then(|launch_window| launch_window.args) {
+                // an error getting the server state would just happen again, so get it anew
+                let state = state.filter(|state| state.is_ok());
+                Task::done(Message::HandleLauncherWindow { config, state, world, menu, wait, window })
             } else {
-                window::close(window)
+                Task::none()
+            },
+            Message::SaveLog(window) => if let Some(launch_window) = self.windows.get(&window) {
+                Task::perform(save_log(launch_window.log.clone()), move |result| Message::LogSaved(window, result.map_err(Arc::new)))
+            } else {
+                Task::none()
             },
-            Message::LaunchMinecraft { config, state, menu, wait } => window::open(window::Settings {
-                size: Size { width: 512.0, height: 128.0 },
+            Message::SignIn { config } => window::open(window::Settings {
+                size: Size { width: 512.0, height: 192.0 },
                 icon: icon::from_file_data(include_bytes!("../assets/wurstpick.ico"), Some(::image::ImageFormat::Ico)).ok(),
                 exit_on_close_request: false,
                 ..window::Settings::default()
-            }).1.map(move |window| Message::HandleLauncherWindow { config: config.clone(), state: state.clone(), menu, wait, window }),
-            Message::Progress(window, text) => {
-                self.progress.insert(window, text);
+            }).1.map(move |window| Message::HandleSignInWindow { config: config.clone(), window }),
+            Message::SignInCode(window, code) => {
+                self.sign_in_windows.entry(window).or_default().code = Some(code);
                 Task::none()
             }
+            Message::SignInDone(window, result) => {
+                self.sign_in_windows.entry(window).or_default().result = Some(result);
+                Task::none()
+            }
+            Message::ToggleErrorDetails(window) => {
+                if let Some(launch_window) = self.windows.get_mut(&window) {
+                    launch_window.show_error_details = !launch_window.show_error_details;
+                }
+                Task::none()
+            }
+            Message::ToggleLog(window) => if let Some(launch_window) = self.windows.get_mut(&window) {
+                launch_window.show_log = !launch_window.show_log;
+                if launch_window.show_log {
+                    window::resize(window, Size { width: 768.0, height: 576.0 })
+                } else {
+                    Task::none()
+                }
+            } else {
+                Task::none()
+            },
         }
     }
 
     fn view(&self, window: window::Id) -> iced::Element<'_, Message> {
-        Column::new()
-            //TODO progress bar
-            .push(self.progress.get(&window).copied().unwrap_or("initializing"))
-            .spacing(8)
-            .padding(8)
-            .into()
+        if let Some(sign_in_window) = self.sign_in_windows.get(&window) {
+            let col = match (&sign_in_window.result, &sign_in_window.code) {
+                (Some(Ok(name)), _) => Column::new()
+                    .push(Text::new(format!("Signed in as {name}.")))
+                    .push(Button::new("Close").on_press(Message::CloseRequested(window))),
+                (Some(Err(e)), _) => Column::new()
+                    .push(Text::new(e.to_string()))
+                    .push(Button::new("Close").on_press(Message::CloseRequested(window))),
+                (None, Some(code)) => Column::new()
+                    .push(Text::new(format!("To sign in, open {} in your browser and enter this code:", code.verification_uri)))
+                    .push(Text::new(code.user_code.as_str()).font(iced::Font::MONOSPACE).size(24))
+                    .push(Row::new()
+                        .push(Button::new("Open in browser").on_press(Message::OpenVerificationUri(window)))
+                        .push(Button::new("Copy code").on_press(Message::CopyUserCode(window)))
+                        .push(Button::new("Cancel").on_press(Message::CloseRequested(window)))
+                        .spacing(8)
+                    ),
+                (None, None) => Column::new()
+                    .push("Contacting Microsoft…"),
+            };
+            return col
+                .spacing(8)
+                .padding(8)
+                .into()
+        }
+        let launch_window = self.windows.get(&window);
+        if let Some(launch_window) = launch_window && let Some(ref error) = launch_window.error {
+            let mut col = Column::new();
+            if let Some(step) = launch_window.failed_step() {
+                col = col.push(Text::new(format!("Failed while {step}:")));
+            }
+            col = col.push(Text::new(error.to_string()));
+            if launch_window.show_error_details {
+                col = col.push(Scrollable::new(Text::new(launch_window.error_details().unwrap_or_default())).height(Length::Fill));
+            }
+            col
+                .push(Row::new()
+                    .push(Button::new("Retry").on_press(Message::Retry(window)))
+                    .push(Button::new("Copy details").on_press(Message::CopyErrorDetails(window)))
+                    .push(Button::new(if launch_window.show_error_details { "Hide details" } else { "Show details" }).on_press(Message::ToggleErrorDetails(window)))
+                    .push(Button::new("Close").on_press(Message::LaunchDone(window)))
+                    .spacing(8)
+                )
+                .push(launch_window.log_view(window))
+                .spacing(8)
+                .padding(8)
+                .into()
+        } else if let Some(report) = launch_window.and_then(|launch_window| launch_window.mod_report.as_ref()) {
+            let mut mod_list = Column::new();
+            for (id, version) in &report.missing {
+                mod_list = mod_list.push(Text::new(format!("missing: {id} {version}")));
+            }
+            for (id, installed, required) in &report.outdated {
+                mod_list = mod_list.push(Text::new(format!("wrong version: {id} {installed} (server has {required})")));
+            }
+            for (id, version) in &report.extra {
+                mod_list = mod_list.push(Text::new(format!("not on server: {id} {version}")));
+            }
+            Column::new()
+                .push("The installed mods don't match the server's mods:")
+                .push(Scrollable::new(mod_list).height(Length::Fill))
+                .push(Row::new()
+                    .push(Button::new("Continue").on_press(Message::ModReportDecision(window, true)))
+                    .push(Button::new("Abort").on_press(Message::ModReportDecision(window, false)))
+                    .spacing(8)
+                )
+                .spacing(8)
+                .padding(8)
+                .into()
+        } else {
+            let progress = launch_window.map(|launch_window| &launch_window.progress);
+            let mut steps = Column::new();
+            for (index, step) in progress.map(|progress| &*progress.steps).unwrap_or_default().iter().enumerate() {
+                let current = progress.and_then(|progress| progress.current);
+                steps = steps.push(Text::new(match current {
+                    Some(current) if index < current => format!("✓ {step}"),
+                    Some(current) if index == current => if let Some(fraction) = progress.and_then(|progress| progress.fraction) {
+                        format!("▶ {step} ({:.0}%)", fraction * 100.0)
+                    } else {
+                        format!("▶ {step}")
+                    },
+                    _ => format!("○ {step}"),
+                }));
+            }
+            let cancelling = launch_window.is_some_and(|launch_window| launch_window.cancelling);
+            let mut col = Column::new()
+                .push(ProgressBar::new(0.0..=1.0, progress.map_or(0.0, Progress::overall)))
+                .push(Scrollable::new(steps).height(Length::Fill))
+                .push(if cancelling {
+                    Row::new()
+                        .push("cancelling…")
+                } else {
+                    Row::new()
+                        .push(Button::new("Cancel").on_press(Message::Cancel(window)))
+                });
+            if let Some(launch_window) = launch_window {
+                col = col.push(launch_window.log_view(window));
+            }
+            col
+                .spacing(8)
+                .padding(8)
+                .into()
+        }
     }
 }
 
@@ -316,9 +1136,11 @@ pub(crate) enum Args {
         rx: broadcast::Receiver<Message>,
     },
     Launch {
+        world: String,
         menu: bool,
         wait: bool,
     },
+    SignIn,
 }
 
 struct RxWrapper(broadcast::Receiver<Message>);
@@ -330,10 +1152,14 @@ impl Hash for RxWrapper {
 pub(crate) fn run(http_client: reqwest::Client, args: Args) -> iced::Result {
     fn theme(_: &Gui, _: window::Id) -> Option<Theme> { wheel::gui::theme() }
 
-    let standalone = match args { Args::Default { .. } => None, Args::Launch { menu, wait } => Some((menu, wait)) };
+    let initial_message = match &args {
+        Args::Default { .. } => None,
+        Args::Launch { world, menu, wait } => Some(Message::LaunchMinecraft { config: None, state: None, world: world.clone(), menu: *menu, wait: *wait }),
+        Args::SignIn => Some(Message::SignIn { config: None }),
+    };
     iced::daemon(move || (
-        Gui::new(http_client.clone(), standalone.is_some()),
-        if let Some((menu, wait)) = standalone { Task::done(Message::LaunchMinecraft { config: None, state: None, menu, wait }) } else { Task::none() },
+        Gui::new(http_client.clone(), initial_message.is_some()),
+        if let Some(message) = initial_message.clone() { Task::done(message) } else { Task::none() },
     ), Gui::update, Gui::view)
         .title(Gui::title)
         .subscription(move |_| Subscription::batch(
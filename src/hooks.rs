@@ -0,0 +1,117 @@
+//! Running the hooks from the `hooks` config entry when something happens on one of the worlds.
+//!
+//! Hooks run in the background with a timeout, and failures are only logged, so a broken hook can't hold up updating the server state.
+
+use {
+    std::time::Duration,
+    tokio::{
+        process::Command,
+        time::timeout,
+    },
+    wheel::traits::{
+        CommandExt as _,
+        ReqwestResponseExt as _,
+    },
+    crate::{
+        config::{
+            Hook,
+            HookAction,
+        },
+        events::Event,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error(transparent)] Io(#[from] std::io::Error),
+    #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("hook command is empty")]
+    EmptyCommand,
+    #[error("hook command exited with {0}")]
+    Exit(std::process::ExitStatus),
+    #[error("hook timed out")]
+    Timeout,
+}
+
+/// The HTTP client for webhooks. Unlike the one for the Wurstmineberg API, this allows plain HTTP since webhooks are often on the local network.
+pub(crate) fn client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " (", env!("CARGO_PKG_REPOSITORY"), ")"))
+        .tls_backend_rustls()
+        .build()
+}
+
+/// Environment variables with the event data, for command hooks.
+fn env_vars(event: &Event) -> Vec<(&'static str, String)> {
+    let mut vars = vec![(
+        "WURSTMINEBERG_EVENT",
+        match event {
+            Event::Join { .. } => "join",
+            Event::Leave { .. } => "leave",
+            Event::ServerUp { .. } => "serverUp",
+            Event::ServerDown { .. } => "serverDown",
+            Event::VersionChange { .. } => "versionChange",
+        }.to_owned(),
+    )];
+    match event {
+        Event::Join { world, player } | Event::Leave { world, player } => {
+            vars.push(("WURSTMINEBERG_WORLD", world.clone()));
+            vars.push(("WURSTMINEBERG_PLAYER_UID", player.uid.clone()));
+            vars.push(("WURSTMINEBERG_PLAYER_NAME", player.name.clone()));
+        }
+        Event::ServerUp { world } | Event::ServerDown { world } => vars.push(("WURSTMINEBERG_WORLD", world.clone())),
+        Event::VersionChange { world, old_version, new_version } => {
+            vars.push(("WURSTMINEBERG_WORLD", world.clone()));
+            vars.push(("WURSTMINEBERG_OLD_VERSION", old_version.clone().unwrap_or_default()));
+            vars.push(("WURSTMINEBERG_NEW_VERSION", new_version.clone().unwrap_or_default()));
+        }
+    }
+    vars
+}
+
+async fn run_hook(http_client: &reqwest::Client, action: &HookAction, event: &Event) -> Result<(), Error> {
+    match action {
+        HookAction::Command { command } => {
+            let (program, args) = command.split_first().ok_or(Error::EmptyCommand)?;
+            let status = Command::new(program)
+                .args(args)
+                .envs(env_vars(event))
+                // so the process is killed on timeout
+                .kill_on_drop(true)
+                .release_create_no_window()
+                .status().await?;
+            if !status.success() { return Err(Error::Exit(status)) }
+        }
+        HookAction::Webhook { url } => {
+            http_client.post(url)
+                .json(event)
+                .send().await?
+                .detailed_error_for_status().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Starts running the hooks that apply to the given events, without waiting for them to finish.
+pub(crate) fn run(http_client: &reqwest::Client, hooks: &[Hook], events: &[Event]) {
+    for event in events {
+        log::info!("event: {event:?}");
+        for hook in hooks {
+            if hook.events.is_empty() || hook.events.contains(&event.kind()) {
+                let http_client = http_client.clone();
+                let hook = hook.clone();
+                let event = event.clone();
+                tokio::spawn(async move {
+                    let result = match timeout(Duration::from_secs(hook.timeout), run_hook(&http_client, &hook.action, &event)).await {
+                        Ok(result) => result,
+                        Err(_) => Err(Error::Timeout),
+                    };
+                    if let Err(e) = result {
+                        log::error!("hook {:?} failed for event {event:?}: {e} ({e:?})", hook.action);
+                    }
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Forwarding commands from another invocation of the app to the tray instance that's already running, so there's only one tray icon and its cached server state is reused.
+//!
+//! Each command is sent as JSON over a separate connection to a local socket (a named pipe on Windows).
+
+use {
+    std::env,
+    interprocess::local_socket::{
+        GenericNamespaced,
+        ListenerOptions,
+        Name,
+        ToNsName as _,
+        tokio::{
+            Listener,
+            Stream,
+        },
+        traits::tokio::{
+            Listener as _,
+            Stream as _,
+        },
+    },
+    log_lock::*,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    tokio::io::{
+        self,
+        AsyncReadExt as _,
+        AsyncWriteExt as _,
+    },
+    wheel::traits::SendResultExt as _,
+    crate::{
+        gui,
+        tray::{
+            self,
+            Action,
+        },
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum Command {
+    Launch {
+        menu: bool,
+        wait: bool,
+        world: String,
+    },
+    /// Shows the tray menu, e.g. when the app is started again while it's already running.
+    ShowMenu,
+    SignIn,
+    OpenSettings,
+    Exit,
+}
+
+/// The socket name includes the user name since named pipes and abstract sockets are shared between users.
+fn name() -> io::Result<Name<'static>> {
+    let user = env::var("USERNAME").or_else(|_| env::var("USER")).unwrap_or_default();
+    format!("{}-{user}.sock", env!("CARGO_PKG_NAME")).to_ns_name::<GenericNamespaced>()
+}
+
+async fn connect() -> Option<Stream> {
+    match Stream::connect(name().ok()?).await {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            log::debug!("no running instance found: {e}");
+            None
+        }
+    }
+}
+
+/// Sends a command to the running instance. Returns `false` if there is none, in which case the command should be handled by this instance.
+pub(crate) async fn forward(command: &Command) -> Result<bool, Error> {
+    let Some(mut stream) = connect().await else { return Ok(false) };
+    stream.write_all(serde_json::to_string_pretty(command)?.as_bytes()).await?;
+    stream.flush().await?;
+    log::info!("forwarded command to running instance: {command:?}");
+    Ok(true)
+}
+
+/// Starts accepting commands from other instances. Must be called from within a Tokio runtime.
+///
+/// Fails if another instance is already listening, so this also ensures there's only one tray icon.
+pub(crate) fn listen() -> Result<Listener, Error> {
+    Ok(ListenerOptions::new().name(name()?).create_tokio()?)
+}
+
+/// Handles commands sent by other instances using the given tray context.
+pub(crate) async fn serve(listener: Listener, context: tray::Context) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("failed to accept connection from another instance: {e}");
+                continue
+            }
+        };
+        let mut buf = String::default();
+        let command = match stream.read_to_string(&mut buf).await {
+            Ok(_) if buf.is_empty() => continue,
+            Ok(_) => match serde_json::from_str::<Command>(&buf) {
+                Ok(command) => command,
+                Err(e) => {
+                    log::warn!("received invalid command from another instance: {e}");
+                    continue
+                }
+            },
+            Err(e) => {
+                log::warn!("failed to receive command from another instance: {e}");
+                continue
+            }
+        };
+        log::info!("received command from another instance: {command:?}");
+        match command {
+            Command::Launch { menu, wait, world } => {
+                let state = lock!(state = context.state; state.clone());
+                context.gui_tx.send(gui::Message::LaunchMinecraft {
+                    config: Some(context.config.clone()),
+                    world, menu, wait, state,
+                }).allow_unreceived();
+            }
+            Command::ShowMenu => context.show_menu.notify_one(),
+            Command::SignIn => context.perform(&Action::SignIn, None),
+            Command::OpenSettings => context.perform(&Action::OpenSettings, None),
+            Command::Exit => context.perform(&Action::Exit, None),
+        }
+    }
+}
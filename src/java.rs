@@ -0,0 +1,208 @@
+//! Choosing the Java runtime and JVM arguments Minecraft is launched with.
+
+use {
+    std::{
+        env,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    tokio::process::Command,
+    wheel::{
+        fs,
+        traits::{
+            CommandExt as _,
+            IoResultExt as _,
+        },
+    },
+    crate::{
+        config,
+        launcher,
+        version::Version,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("{} is Java {found}, but this Minecraft version requires Java {required} or newer", .path.display())]
+    TooOld {
+        path: PathBuf,
+        found: u32,
+        required: u32,
+    },
+    #[error("failed to determine the version of {}", .0.display())]
+    UnknownVersion(PathBuf),
+}
+
+/// The oldest major Java version that can run the given Minecraft version, if the version is known.
+pub(crate) fn required_version(game_version: Option<&str>) -> Option<u32> {
+    let version = Version::parse(game_version?)?;
+    Some(if version.is_at_least((1, 20, 5), (24, 14)) {
+        21
+    } else if version.is_at_least((1, 18, 0), (21, 37)) {
+        17
+    } else if version.is_at_least((1, 17, 0), (21, 19)) {
+        16
+    } else {
+        8
+    })
+}
+
+/// Runs `java -version` to find out the major version of the given Java executable.
+async fn major_version(path: &Path) -> Result<u32, Error> {
+    let output = Command::new(path).arg("-version").release_create_no_window().output().await.at_command("java -version")?;
+    // the version is printed to stderr, e.g. `openjdk version "17.0.9" 2023-10-17` or `java version "1.8.0_391"`
+    parse_major_version(&String::from_utf8_lossy(&output.stderr)).ok_or_else(|| Error::UnknownVersion(path.to_owned()))
+}
+
+fn parse_major_version(output: &str) -> Option<u32> {
+    let version = output.split('"').nth(1)?;
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Java executables in `JAVA_HOME`, the standard install locations on this platform, and the `PATH`, in order of preference.
+async fn candidates() -> Result<Vec<PathBuf>, Error> {
+    let exe = if cfg!(windows) { "java.exe" } else { "java" };
+    let mut candidates = Vec::default();
+    if let Some(java_home) = env::var_os("JAVA_HOME") {
+        candidates.push(PathBuf::from(java_home).join("bin").join(exe));
+    }
+    let install_dirs = if cfg!(windows) {
+        vec![
+            PathBuf::from("C:\\Program Files\\Java"),
+            PathBuf::from("C:\\Program Files\\Eclipse Adoptium"),
+            PathBuf::from("C:\\Program Files\\Microsoft"),
+            PathBuf::from("C:\\Program Files\\Zulu"),
+        ]
+    } else if cfg!(target_os = "macos") {
+        vec![PathBuf::from("/Library/Java/JavaVirtualMachines")]
+    } else {
+        vec![PathBuf::from("/usr/lib/jvm")]
+    };
+    for install_dir in install_dirs {
+        if !fs::exists(&install_dir).await? { continue }
+        let runtimes = tokio::task::spawn_blocking(move || {
+            let mut runtimes = Vec::default();
+            for entry in std::fs::read_dir(&install_dir).at(&install_dir)? {
+                runtimes.push(entry.at(&install_dir)?.path());
+            }
+            runtimes.sort();
+            Ok::<_, wheel::Error>(runtimes)
+        }).await??;
+        for runtime in runtimes {
+            candidates.push(if cfg!(target_os = "macos") {
+                runtime.join("Contents").join("Home").join("bin").join(exe)
+            } else {
+                runtime.join("bin").join(exe)
+            });
+        }
+    }
+    candidates.push(PathBuf::from(exe));
+    Ok(candidates)
+}
+
+/// The Java executable to launch Minecraft with, or `None` to let the launcher decide.
+///
+/// A configured `path` is checked against the `required` major version. Otherwise, if `autoSelect` is enabled, an installed Java with exactly the required version is preferred, followed by the oldest newer one.
+pub(crate) async fn select(config: &config::Java, required: Option<u32>) -> Result<Option<PathBuf>, Error> {
+    if let Some(ref path) = config.path {
+        if let Some(required) = required {
+            let found = major_version(path).await?;
+            if found < required {
+                return Err(Error::TooOld { path: path.clone(), found, required })
+            }
+        }
+        return Ok(Some(path.clone()))
+    }
+    if !config.auto_select { return Ok(None) }
+    let Some(required) = required else { return Ok(None) };
+    let mut best = None::<(u32, PathBuf)>;
+    for candidate in candidates().await? {
+        // candidates that don't exist or fail to run are skipped
+        let Ok(found) = major_version(&candidate).await else { continue };
+        log::debug!("found Java {found} at {}", candidate.display());
+        if found == required {
+            return Ok(Some(candidate))
+        }
+        if found > required && best.as_ref().is_none_or(|(best_found, _)| found < *best_found) {
+            best = Some((found, candidate));
+        }
+    }
+    Ok(best.map(|(_, path)| path))
+}
+
+/// The JVM arguments for the configured heap sizes, followed by the configured extra arguments.
+pub(crate) fn jvm_args(config: &config::Java) -> Vec<String> {
+    let mut args = Vec::default();
+    if let Some(min_memory) = config.min_memory {
+        args.push(format!("-Xms{min_memory}M"));
+    }
+    if let Some(max_memory) = config.max_memory {
+        args.push(format!("-Xmx{max_memory}M"));
+    }
+    args.extend(config.args.iter().cloned());
+    args
+}
+
+/// Writes the Java settings into the `instance.cfg` of the Prism Launcher instance at `instance_dir`. Settings that aren't configured are left alone.
+pub(crate) async fn configure_prism(instance_dir: &Path, java_path: Option<&Path>, config: &config::Java) -> Result<(), Error> {
+    let mut settings = Vec::default();
+    if let Some(java_path) = java_path {
+        settings.push(("OverrideJavaLocation", format!("true")));
+        settings.push(("JavaPath", java_path.to_string_lossy().replace('\\', "/")));
+    }
+    if config.min_memory.is_some() || config.max_memory.is_some() {
+        settings.push(("OverrideMemory", format!("true")));
+        if let Some(min_memory) = config.min_memory {
+            settings.push(("MinMemAlloc", min_memory.to_string()));
+        }
+        if let Some(max_memory) = config.max_memory {
+            settings.push(("MaxMemAlloc", max_memory.to_string()));
+        }
+    }
+    if !config.args.is_empty() {
+        settings.push(("OverrideJavaArgs", format!("true")));
+        settings.push(("JvmArgs", config.args.join(" ")));
+    }
+    launcher::write_prism_settings(instance_dir, settings).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_versions() {
+        assert_eq!(required_version(None), None);
+        assert_eq!(required_version(Some("not a version")), None);
+        assert_eq!(required_version(Some("1.12.2")), Some(8));
+        assert_eq!(required_version(Some("1.16.5")), Some(8));
+        assert_eq!(required_version(Some("21w18a")), Some(8));
+        assert_eq!(required_version(Some("21w19a")), Some(16));
+        assert_eq!(required_version(Some("1.17.1")), Some(16));
+        assert_eq!(required_version(Some("1.18-pre1")), Some(17));
+        assert_eq!(required_version(Some("1.20.4")), Some(17));
+        assert_eq!(required_version(Some("24w14a")), Some(21));
+        assert_eq!(required_version(Some("1.20.5")), Some(21));
+        assert_eq!(required_version(Some("1.21.4")), Some(21));
+    }
+
+    #[test]
+    fn major_versions() {
+        assert_eq!(parse_major_version("openjdk version \"17.0.9\" 2023-10-17\nOpenJDK Runtime Environment"), Some(17));
+        assert_eq!(parse_major_version("java version \"1.8.0_391\"\nJava(TM) SE Runtime Environment"), Some(8));
+        assert_eq!(parse_major_version("openjdk version \"21\" 2023-09-19"), Some(21));
+        assert_eq!(parse_major_version("openjdk version \"22-ea\" 2024-03-19"), Some(22));
+        assert_eq!(parse_major_version("openjdk version \"11.0.2+9\""), Some(11));
+        assert_eq!(parse_major_version("no version here"), None);
+        assert_eq!(parse_major_version("openjdk version \"unknown\""), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! The tasks in the app's taskbar jump list. Which tasks are shown is decided independently of the platform, applying them is only implemented on Windows.
+
+#[cfg(any(windows, test))] use {
+    itertools::Itertools as _,
+    crate::{
+        MAIN_WORLD,
+        tray::SharedState,
+    },
+};
+#[cfg(windows)] use {
+    std::fs,
+    wheel::traits::IoResultExt as _,
+    windows::{
+        Foundation::Uri,
+        UI::StartScreen::{
+            JumpList,
+            JumpListItem,
+        },
+    },
+    crate::config,
+};
+
+/// The URL opened by the “Open map” task.
+pub(crate) const MAP_URL: &str = "https://map.wurstmineberg.de/";
+
+#[cfg(windows)]
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Config(#[from] config::Error),
+    #[error(transparent)] Io(#[from] std::io::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error(transparent)] Windows(#[from] windows::core::Error),
+}
+
+/// Which of the app's icons is shown next to a task.
+#[cfg(any(windows, test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Logo {
+    /// The Wurstmineberg logo.
+    Wurstmineberg,
+    /// The app icon.
+    Wurstpick,
+}
+
+#[cfg(windows)]
+impl Logo {
+    /// The file name and contents of the icon. These are the same files that are embedded as resources by the build script.
+    fn icon(&self, is_light: bool) -> (&'static str, &'static [u8]) {
+        match (self, is_light) {
+            (Self::Wurstmineberg, true) => ("logo-black-32.ico", include_bytes!("../assets/logo-black-32.ico")),
+            (Self::Wurstmineberg, false) => ("logo-white-32.ico", include_bytes!("../assets/logo-white-32.ico")),
+            (Self::Wurstpick, _) => ("wurstpick.ico", include_bytes!("../assets/wurstpick.ico")),
+        }
+    }
+}
+
+#[cfg(any(windows, test))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Task {
+    pub(crate) title: String,
+    /// The command-line arguments the app is started with when the task is selected.
+    pub(crate) args: Vec<String>,
+    pub(crate) logo: Logo,
+}
+
+#[cfg(any(windows, test))]
+impl Task {
+    fn new(title: impl Into<String>, args: impl IntoIterator<Item = impl Into<String>>, logo: Logo) -> Self {
+        Self {
+            title: title.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            logo,
+        }
+    }
+
+    /// The arguments as a single command line, quoting arguments containing spaces.
+    #[cfg(windows)]
+    fn command_line(&self) -> String {
+        self.args.iter().map(|arg| if arg.contains(' ') { format!("\"{arg}\"") } else { arg.clone() }).join(" ")
+    }
+}
+
+/// The jump list tasks for the given server state: one for joining each running world, with the main world first, followed by tasks that are always shown.
+#[cfg(any(windows, test))]
+pub(crate) fn tasks(state: &SharedState) -> Vec<Task> {
+    let mut tasks = Vec::default();
+    if let Some(Ok((_, ref statuses))) = *state {
+        for (world_name, _) in statuses.iter().filter(|(_, status)| status.running).sorted_by_key(|&(world_name, _)| (world_name != MAIN_WORLD, world_name)) {
+            tasks.push(Task::new(format!("Join {world_name}"), ["launch", "--world", world_name], Logo::Wurstmineberg));
+        }
+    }
+    tasks.push(Task::new("Minecraft Main Menu", ["launch", "--menu"], Logo::Wurstpick));
+    tasks.push(Task::new("Open map", ["open-map"], Logo::Wurstmineberg));
+    tasks.push(Task::new("Settings", ["open-settings"], Logo::Wurstpick));
+    tasks.push(Task::new("Open log", ["open-log-folder"], Logo::Wurstpick));
+    tasks
+}
+
+/// Writes the icon to the data folder and returns its URI.
+///
+/// Jump list logos can't refer to embedded resources directly, and apps that aren't packaged can't use `ms-appx:` URIs.
+#[cfg(windows)]
+fn logo_uri(logo: Logo, is_light: bool) -> Result<Uri, Error> {
+    let (file_name, contents) = logo.icon(is_light);
+    let dir = config::data_dir()?.join("icons");
+    fs::create_dir_all(&dir).at(&dir)?;
+    let path = dir.join(file_name);
+    fs::write(&path, contents).at(&path)?;
+    Ok(Uri::CreateUri(&format!("file:///{}", path.display().to_string().replace('\\', "/")).into())?)
+}
+
+/// Replaces the jump list with the given tasks. Does nothing if jump lists aren't supported.
+#[cfg(windows)]
+pub(crate) async fn configure(tasks: &[Task]) -> Result<(), Error> {
+    if !JumpList::IsSupported()? { return Ok(()) }
+    let is_light = crate::tray::uses_light_theme();
+    let list = JumpList::LoadCurrentAsync()?.await?;
+    let items = list.Items()?;
+    items.Clear()?;
+    for task in tasks {
+        let item = JumpListItem::CreateWithArguments(&task.command_line().into(), &task.title.as_str().into())?;
+        // a missing logo shouldn't prevent the task from being shown
+        match logo_uri(task.logo, is_light) {
+            Ok(uri) => if let Err(e) = item.SetLogo(&uri) {
+                log::debug!("failed to set jump list logo for {:?}: {e}", task.title);
+            },
+            Err(e) => log::debug!("failed to prepare jump list logo for {:?}: {e} ({e:?})", task.title),
+        }
+        items.Append(&item)?;
+    }
+    list.SaveAsync()?.await?;
+    Ok(())
+}
+
+/// Returns a callback for [`crate::maintain`] which regenerates the jump list whenever the tasks change.
+#[cfg(windows)]
+pub(crate) fn updater(runtime: tokio::runtime::Handle) -> impl FnMut(&SharedState) + Send + 'static {
+    let mut previous_tasks = None;
+    move |state| {
+        let tasks = tasks(state);
+        if previous_tasks.as_ref() == Some(&tasks) { return }
+        previous_tasks = Some(tasks.clone());
+        let runtime = runtime.clone();
+        // driven on a blocking thread so slow WinRT calls don't hold up updating the server state
+        tokio::task::spawn_blocking(move || if let Err(e) = runtime.block_on(configure(&tasks)) {
+            log::warn!("failed to update jump list: {e} ({e:?})");
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::collections::HashMap,
+        crate::WorldStatus,
+        super::*,
+    };
+
+    /// The tasks that are shown regardless of the server state.
+    fn fixed_tasks() -> Vec<Task> {
+        vec![
+            Task::new("Minecraft Main Menu", ["launch", "--menu"], Logo::Wurstpick),
+            Task::new("Open map", ["open-map"], Logo::Wurstmineberg),
+            Task::new("Settings", ["open-settings"], Logo::Wurstpick),
+            Task::new("Open log", ["open-log-folder"], Logo::Wurstpick),
+        ]
+    }
+
+    fn state(worlds: &[(&str, bool)]) -> SharedState {
+        Some(Ok((HashMap::default(), worlds.iter().map(|&(world_name, running)| (world_name.to_owned(), WorldStatus {
+            address: None,
+            loader: None,
+            list: Vec::default(),
+            version: None,
+            running,
+        })).collect())))
+    }
+
+    fn join_task(world_name: &str) -> Task {
+        Task::new(format!("Join {world_name}"), ["launch", "--world", world_name], Logo::Wurstmineberg)
+    }
+
+    #[test]
+    fn fixed_tasks_while_loading() {
+        assert_eq!(tasks(&None), fixed_tasks());
+    }
+
+    #[test]
+    fn running_worlds_sorted() {
+        let running = tasks(&state(&[("creative", true), ("builds", true), (MAIN_WORLD, true)]));
+        assert_eq!(running[..3], [join_task(MAIN_WORLD), join_task("builds"), join_task("creative")]);
+        assert_eq!(running[3..], fixed_tasks());
+    }
+
+    #[test]
+    fn offline_worlds_skipped() {
+        let main_offline = tasks(&state(&[("creative", true), (MAIN_WORLD, false)]));
+        assert_eq!(main_offline[..1], [join_task("creative")]);
+        assert_eq!(main_offline[1..], fixed_tasks());
+        assert_eq!(tasks(&state(&[(MAIN_WORLD, false)])), fixed_tasks());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn command_line_quotes_spaces() {
+        assert_eq!(join_task("Test World").command_line(), r#"launch --world "Test World""#);
+    }
+}
@@ -1,12 +1,33 @@
 use {
-    std::collections::BTreeMap,
+    std::{
+        collections::BTreeMap,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    directories::BaseDirs,
     serde::{
         Deserialize,
         Serialize,
     },
     serde_json::Value as Json,
+    wheel::{
+        fs,
+        traits::IoResultExt as _,
+    },
+    crate::config::Config,
 };
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("failed to find user folder")]
+    BaseDirs,
+    #[error("failed to find the Minecraft folder, consider setting `minecraftDir` in the config")]
+    MinecraftDir,
+}
+
 #[derive(Deserialize, Serialize)]
 pub(crate) struct Data {
     pub(crate) profiles: BTreeMap<String, Profile>,
@@ -21,3 +42,193 @@ pub(crate) struct Profile {
     #[serde(flatten)]
     _extra: BTreeMap<String, Json>,
 }
+
+/// The folders relative to which the standard Minecraft and Prism Launcher locations are searched.
+///
+/// Constructed from the user's actual folders by [`SearchRoots::new`], but can point anywhere else, e.g. a fake directory tree.
+pub(crate) struct SearchRoots {
+    pub(crate) home: PathBuf,
+    pub(crate) data: PathBuf,
+}
+
+impl SearchRoots {
+    pub(crate) fn new() -> Result<Self, Error> {
+        let base_dirs = BaseDirs::new().ok_or(Error::BaseDirs)?;
+        Ok(Self {
+            home: base_dirs.home_dir().to_owned(),
+            data: base_dirs.data_dir().to_owned(),
+        })
+    }
+
+    /// Standard locations of the `.minecraft` folder on this platform, in order of preference.
+    pub(crate) fn minecraft_dirs(&self) -> Vec<PathBuf> {
+        if cfg!(windows) {
+            vec![self.data.join(".minecraft")]
+        } else if cfg!(target_os = "macos") {
+            vec![self.data.join("minecraft")]
+        } else {
+            vec![
+                self.home.join(".minecraft"),
+                self.home.join(".var").join("app").join("com.mojang.Minecraft").join(".minecraft"), // Flatpak
+                self.home.join("snap").join("mc-installer").join("current").join(".minecraft"), // Snap
+            ]
+        }
+    }
+
+    /// Standard locations of Prism Launcher's data folder on this platform, in order of preference.
+    pub(crate) fn prism_dirs(&self) -> Vec<PathBuf> {
+        if cfg!(windows) {
+            vec![
+                self.data.join("PrismLauncher"),
+                self.home.join("scoop").join("persist").join("prismlauncher"), // Scoop installs are portable
+            ]
+        } else if cfg!(target_os = "macos") {
+            vec![self.data.join("PrismLauncher")]
+        } else {
+            vec![
+                self.data.join("PrismLauncher"),
+                self.home.join(".var").join("app").join("org.prismlauncher.PrismLauncher").join("data").join("PrismLauncher"), // Flatpak
+                self.home.join("snap").join("prismlauncher").join("current").join(".local").join("share").join("PrismLauncher"), // Snap
+            ]
+        }
+    }
+
+    /// The Minecraft folder configured via `minecraftDir`, or else the first standard location that exists.
+    pub(crate) async fn minecraft_dir(&self, config: &Config) -> Result<Option<PathBuf>, Error> {
+        if let Some(ref minecraft_dir) = config.minecraft_dir {
+            return Ok(Some(minecraft_dir.clone()))
+        }
+        first_existing(self.minecraft_dirs()).await
+    }
+
+    /// The first standard location of Prism Launcher's data folder that exists.
+    pub(crate) async fn prism_dir(&self) -> Result<Option<PathBuf>, Error> {
+        first_existing(self.prism_dirs()).await
+    }
+
+    /// The folder of the Prism Launcher instance configured via `prismInstance`, if any.
+    pub(crate) async fn prism_instance(&self, config: &Config) -> Result<Option<PathBuf>, Error> {
+        let Some(ref instance) = config.prism_instance else { return Ok(None) };
+        let Some(prism_dir) = self.prism_dir().await? else { return Ok(None) };
+        first_existing([prism_dir.join("instances").join(instance)]).await
+    }
+
+    /// The game folder of the Prism Launcher instance configured via `prismInstance`, if any.
+    pub(crate) async fn prism_instance_dir(&self, config: &Config) -> Result<Option<PathBuf>, Error> {
+        let Some(instance_dir) = self.prism_instance(config).await? else { return Ok(None) };
+        // older instances use `minecraft` instead of `.minecraft`
+        first_existing([instance_dir.join(".minecraft"), instance_dir.join("minecraft")]).await
+    }
+}
+
+async fn first_existing(candidates: impl IntoIterator<Item = PathBuf>) -> Result<Option<PathBuf>, Error> {
+    for candidate in candidates {
+        if fs::exists(&candidate).await? {
+            return Ok(Some(candidate))
+        }
+    }
+    Ok(None)
+}
+
+/// The path to the official launcher's profile list inside the given Minecraft folder.
+pub(crate) async fn data_path(minecraft_dir: &Path) -> Result<PathBuf, Error> {
+    let path = minecraft_dir.join("launcher_profiles_microsoft_store.json");
+    Ok(if fs::exists(&path).await? {
+        path
+    } else {
+        minecraft_dir.join("launcher_profiles.json")
+    })
+}
+
+/// Sets the given entries in a Prism Launcher instance's `instance.cfg`, keeping all other entries.
+pub(crate) async fn write_prism_settings(instance_dir: &Path, settings: Vec<(&str, String)>) -> Result<(), wheel::Error> {
+    if settings.is_empty() { return Ok(()) }
+    let path = instance_dir.join("instance.cfg");
+    let mut lines = String::from_utf8_lossy(&fs::read(&path).await.missing_ok()?).lines().map(str::to_owned).collect::<Vec<_>>();
+    for (key, value) in settings {
+        let line = format!("{key}={}", ini_value(&value));
+        if let Some(existing) = lines.iter_mut().find(|line| line.split_once('=').is_some_and(|(existing_key, _)| existing_key.trim() == key)) {
+            *existing = line;
+        } else {
+            lines.push(line);
+        }
+    }
+    let mut buf = lines.join("\n");
+    buf.push('\n');
+    fs::write(path, buf).await?;
+    Ok(())
+}
+
+/// Quotes a value for a Qt-style INI file if necessary.
+fn ini_value(value: &str) -> String {
+    if value.contains([';', ',', '=', '"', '\\']) || value.starts_with(' ') || value.ends_with(' ') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::test_util,
+        super::*,
+    };
+
+    async fn create(path: &Path) {
+        fs::create_dir_all(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn minecraft_dir_prefers_first_existing() {
+        let root = test_util::temp_dir("launcher-minecraft");
+        let roots = SearchRoots { home: root.join("home"), data: root.join("data") };
+        let config = Config::default();
+        assert_eq!(roots.minecraft_dir(&config).await.unwrap(), None);
+        let candidates = roots.minecraft_dirs();
+        let last = candidates.last().unwrap();
+        create(last).await;
+        assert_eq!(roots.minecraft_dir(&config).await.unwrap().as_ref(), Some(last));
+        create(&candidates[0]).await;
+        assert_eq!(roots.minecraft_dir(&config).await.unwrap().as_ref(), Some(&candidates[0]));
+    }
+
+    #[tokio::test]
+    async fn minecraft_dir_from_config() {
+        let root = test_util::temp_dir("launcher-minecraft-config");
+        let roots = SearchRoots { home: root.join("home"), data: root.join("data") };
+        let config = Config {
+            minecraft_dir: Some(PathBuf::from("/nonexistent/minecraft")),
+            ..Config::default()
+        };
+        // the configured folder is used even if it doesn't exist
+        assert_eq!(roots.minecraft_dir(&config).await.unwrap(), Some(PathBuf::from("/nonexistent/minecraft")));
+    }
+
+    #[tokio::test]
+    async fn prism_instance_dir() {
+        let root = test_util::temp_dir("launcher-prism");
+        let roots = SearchRoots { home: root.join("home"), data: root.join("data") };
+        let mut config = Config::default();
+        let instance = roots.prism_dirs()[0].join("instances").join("Wurstmineberg");
+        create(&instance.join("minecraft")).await;
+        assert_eq!(roots.prism_instance_dir(&config).await.unwrap(), None);
+        config.prism_instance = Some(format!("Wurstmineberg"));
+        assert_eq!(roots.prism_instance_dir(&config).await.unwrap(), Some(instance.join("minecraft")));
+        create(&instance.join(".minecraft")).await;
+        assert_eq!(roots.prism_instance_dir(&config).await.unwrap(), Some(instance.join(".minecraft")));
+        config.prism_instance = Some(format!("Other"));
+        assert_eq!(roots.prism_instance_dir(&config).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn data_path_prefers_microsoft_store() {
+        let root = test_util::temp_dir("launcher-data-path");
+        let roots = SearchRoots { home: root.join("home"), data: root.join("data") };
+        let minecraft_dir = roots.home.join(".minecraft");
+        create(&minecraft_dir).await;
+        assert_eq!(data_path(&minecraft_dir).await.unwrap(), minecraft_dir.join("launcher_profiles.json"));
+        fs::write(minecraft_dir.join("launcher_profiles_microsoft_store.json"), "{\"profiles\":{}}").await.unwrap();
+        assert_eq!(data_path(&minecraft_dir).await.unwrap(), minecraft_dir.join("launcher_profiles_microsoft_store.json"));
+    }
+}
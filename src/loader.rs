@@ -0,0 +1,213 @@
+//! Mod loaders and how to select them in each launcher.
+
+use {
+    std::{
+        collections::BTreeMap,
+        fmt,
+        path::Path,
+    },
+    serde::{
+        Deserialize,
+        Deserializer,
+        Serialize,
+        de::{
+            IntoDeserializer as _,
+            value,
+        },
+    },
+    serde_json::Value as Json,
+    wheel::fs,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("no {0} version known for the Prism Launcher instance, consider setting one in the `modLoaders` config entry")]
+    PrismLoaderVersion(Loader),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Loader {
+    Fabric,
+    Quilt,
+    NeoForge,
+    Vanilla,
+}
+
+/// Deserializes an optional loader name, treating loaders this version of the app doesn't know about like a missing one, so a new loader on the server doesn't break reading the server state.
+pub(crate) fn deserialize_known<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Loader>, D::Error> {
+    let loader = Option::<String>::deserialize(deserializer)?;
+    Ok(loader.and_then(|loader| {
+        let deserializer: value::StrDeserializer<'_, value::Error> = loader.as_str().into_deserializer();
+        Loader::deserialize(deserializer).ok()
+    }))
+}
+
+/// Prism Launcher component UIDs of all supported mod loaders, plus Forge so it's removed when switching loaders.
+const PRISM_LOADER_UIDS: [&str; 4] = ["net.fabricmc.fabric-loader", "org.quiltmc.quilt-loader", "net.neoforged", "net.minecraftforge"];
+const PRISM_INTERMEDIARY_UID: &str = "net.fabricmc.intermediary";
+
+impl Loader {
+    /// Removes the loader and its version from a packwiz pack's `[versions]` table.
+    pub(crate) fn from_packwiz(versions: &mut BTreeMap<String, String>) -> Option<(Self, String)> {
+        Self::from_versions(versions, [(Self::Fabric, "fabric"), (Self::Quilt, "quilt"), (Self::NeoForge, "neoforge")])
+    }
+
+    /// Removes the loader and its version from a Modrinth modpack's dependencies.
+    pub(crate) fn from_mrpack(dependencies: &mut BTreeMap<String, String>) -> Option<(Self, String)> {
+        Self::from_versions(dependencies, [(Self::Fabric, "fabric-loader"), (Self::Quilt, "quilt-loader"), (Self::NeoForge, "neoforge")])
+    }
+
+    fn from_versions(versions: &mut BTreeMap<String, String>, keys: [(Self, &str); 3]) -> Option<(Self, String)> {
+        keys.into_iter().find_map(|(loader, key)| Some((loader, versions.remove(key)?)))
+    }
+
+    /// The version argument for `portablemc start`. Without a game version, the latest release is used.
+    pub(crate) fn portablemc_version(&self, game_version: Option<&str>, loader_version: Option<&str>) -> String {
+        match (self, game_version, loader_version) {
+            (Self::Vanilla, Some(game_version), _) => game_version.to_owned(),
+            (Self::Vanilla, None, _) => format!("release"),
+            // NeoForge versions identify the game version, so portablemc accepts either
+            (Self::NeoForge, _, Some(loader_version)) => format!("neoforge:{loader_version}"),
+            (Self::NeoForge, game_version, None) => format!("neoforge:{}", game_version.unwrap_or_default()),
+            (Self::Fabric, Some(game_version), Some(loader_version)) => format!("fabric:{game_version}:{loader_version}"),
+            (Self::Fabric, game_version, _) => format!("fabric:{}", game_version.unwrap_or_default()),
+            (Self::Quilt, Some(game_version), Some(loader_version)) => format!("quilt:{game_version}:{loader_version}"),
+            (Self::Quilt, game_version, _) => format!("quilt:{}", game_version.unwrap_or_default()),
+        }
+    }
+
+    fn prism_uid(&self) -> Option<&'static str> {
+        match self {
+            Self::Fabric => Some("net.fabricmc.fabric-loader"),
+            Self::Quilt => Some("org.quiltmc.quilt-loader"),
+            Self::NeoForge => Some("net.neoforged"),
+            Self::Vanilla => None,
+        }
+    }
+
+    fn uses_intermediary(&self) -> bool {
+        matches!(self, Self::Fabric | Self::Quilt)
+    }
+}
+
+impl fmt::Display for Loader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fabric => write!(f, "Fabric"),
+            Self::Quilt => write!(f, "Quilt"),
+            Self::NeoForge => write!(f, "NeoForge"),
+            Self::Vanilla => write!(f, "vanilla"),
+        }
+    }
+}
+
+/// A Prism Launcher instance's `mmc-pack.json`, which lists the components making up the game.
+#[derive(Deserialize, Serialize)]
+struct MmcPack {
+    components: Vec<Component>,
+    #[serde(flatten)]
+    _extra: BTreeMap<String, Json>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Component {
+    uid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(flatten)]
+    _extra: BTreeMap<String, Json>,
+}
+
+impl Component {
+    fn new(uid: &str, version: String) -> Self {
+        Self {
+            uid: uid.to_owned(),
+            version: Some(version),
+            _extra: BTreeMap::default(),
+        }
+    }
+
+    fn set_version(&mut self, version: String) {
+        if self.version.as_ref() != Some(&version) {
+            self.version = Some(version);
+            // Prism Launcher fills these in again from its metadata
+            self._extra.retain(|key, _| !key.starts_with("cached"));
+        }
+    }
+}
+
+/// Updates the components of the Prism Launcher instance at `instance_dir` to the given game version and mod loader.
+///
+/// If `loader_version` is `None`, the instance's current version of the loader is kept.
+pub(crate) async fn set_prism_components(instance_dir: &Path, game_version: &str, loader: Loader, loader_version: Option<&str>) -> Result<(), Error> {
+    let path = instance_dir.join("mmc-pack.json");
+    let mut pack = fs::read_json::<MmcPack>(&path).await?;
+    let loader_uid = loader.prism_uid();
+    let loader_version = if let Some(loader_version) = loader_version {
+        Some(loader_version.to_owned())
+    } else if let Some(loader_uid) = loader_uid {
+        Some(pack.components.iter().find(|component| component.uid == loader_uid).and_then(|component| component.version.clone()).ok_or(Error::PrismLoaderVersion(loader))?)
+    } else {
+        None
+    };
+    pack.components.retain(|component| if component.uid == PRISM_INTERMEDIARY_UID {
+        loader.uses_intermediary()
+    } else {
+        !PRISM_LOADER_UIDS.contains(&&*component.uid) || Some(&*component.uid) == loader_uid
+    });
+    let minecraft_index = if let Some(index) = pack.components.iter().position(|component| component.uid == "net.minecraft") {
+        pack.components[index].set_version(game_version.to_owned());
+        index
+    } else {
+        pack.components.insert(0, Component::new("net.minecraft", game_version.to_owned()));
+        0
+    };
+    if loader.uses_intermediary() {
+        if let Some(intermediary) = pack.components.iter_mut().find(|component| component.uid == PRISM_INTERMEDIARY_UID) {
+            intermediary.set_version(game_version.to_owned());
+        } else {
+            let mut intermediary = Component::new(PRISM_INTERMEDIARY_UID, game_version.to_owned());
+            intermediary._extra.insert(format!("dependencyOnly"), Json::Bool(true));
+            pack.components.insert(minecraft_index + 1, intermediary);
+        }
+    }
+    if let (Some(loader_uid), Some(loader_version)) = (loader_uid, loader_version) {
+        if let Some(component) = pack.components.iter_mut().find(|component| component.uid == loader_uid) {
+            component.set_version(loader_version);
+        } else {
+            pack.components.push(Component::new(loader_uid, loader_version));
+        }
+    }
+    let mut buf = serde_json::to_string_pretty(&pack)?;
+    buf.push('\n');
+    fs::write(path, buf).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Status {
+        #[serde(default, deserialize_with = "deserialize_known")]
+        loader: Option<Loader>,
+    }
+
+    #[test]
+    fn known_loaders() {
+        assert_eq!(serde_json::from_str::<Status>(r#"{"loader": "fabric"}"#).unwrap().loader, Some(Loader::Fabric));
+        assert_eq!(serde_json::from_str::<Status>(r#"{"loader": "neoforge"}"#).unwrap().loader, Some(Loader::NeoForge));
+        assert_eq!(serde_json::from_str::<Status>(r#"{"loader": "vanilla"}"#).unwrap().loader, Some(Loader::Vanilla));
+    }
+
+    #[test]
+    fn unknown_or_missing_loader() {
+        assert_eq!(serde_json::from_str::<Status>(r#"{"loader": "forge"}"#).unwrap().loader, None);
+        assert_eq!(serde_json::from_str::<Status>(r#"{"loader": null}"#).unwrap().loader, None);
+        assert_eq!(serde_json::from_str::<Status>("{}").unwrap().loader, None);
+    }
+}
@@ -0,0 +1,133 @@
+//! The app's log file, which is rotated once it gets too large.
+
+use {
+    std::{
+        fs::{
+            self,
+            File,
+            OpenOptions,
+        },
+        io::{
+            self,
+            prelude::*,
+        },
+        path::PathBuf,
+        sync::{
+            Mutex,
+            PoisonError,
+        },
+    },
+    chrono::Local,
+    log::{
+        LevelFilter,
+        Log,
+        Metadata,
+        Record,
+    },
+    wheel::traits::IoResultExt as _,
+    crate::config,
+};
+
+/// The log file is rotated once it would exceed this size in bytes.
+const MAX_SIZE: u64 = 1024 * 1024;
+/// The number of rotated log files kept in addition to the current one.
+const KEPT_FILES: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Config(#[from] config::Error),
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] SetLogger(#[from] log::SetLoggerError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+}
+
+/// The folder containing the app's log files as well as logs saved from launch windows.
+pub(crate) fn dir() -> Result<PathBuf, config::Error> {
+    Ok(config::data_dir()?.join("logs"))
+}
+
+/// Opens the log folder in the system's file manager, creating it if necessary.
+pub(crate) fn open_dir() -> Result<(), Error> {
+    let dir = dir()?;
+    fs::create_dir_all(&dir).at(&dir)?;
+    open::that(dir)?;
+    Ok(())
+}
+
+/// Sets up logging to the log file with the given maximum level.
+pub(crate) fn init(level: LevelFilter) -> Result<(), Error> {
+    let dir = dir()?;
+    fs::create_dir_all(&dir).at(&dir)?;
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::default(),
+        level, dir,
+    }))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+struct FileLogger {
+    level: LevelFilter,
+    dir: PathBuf,
+    /// The current log file and its size, opened on first use.
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl FileLogger {
+    /// The path of the current log file for `index` 0, or of a rotated log file otherwise. Higher indices are older.
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join("wurstmineberg.log")
+        } else {
+            self.dir.join(format!("wurstmineberg.{index}.log"))
+        }
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        for index in (1..KEPT_FILES).rev() {
+            match fs::rename(self.path(index), self.path(index + 1)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        fs::rename(self.path(0), self.path(1))
+    }
+
+    fn write(&self, file: &mut Option<(File, u64)>, line: &[u8]) -> io::Result<()> {
+        if file.as_ref().is_some_and(|(_, size)| *size + line.len() as u64 > MAX_SIZE) {
+            // close the file before renaming it, which would fail on Windows otherwise
+            *file = None;
+            self.rotate()?;
+        }
+        if file.is_none() {
+            let new_file = OpenOptions::new().create(true).append(true).open(self.path(0))?;
+            let size = new_file.metadata()?.len();
+            *file = Some((new_file, size));
+        }
+        let (file, size) = file.as_mut().expect("log file was just opened");
+        file.write_all(line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) { return }
+        let line = format!("{} {:<5} {}: {}\n", Local::now().format("%Y-%m-%d %H:%M:%S%.3f"), record.level(), record.target(), record.args());
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        // there's nowhere to report errors writing the log file
+        let _ = self.write(&mut file, line.as_bytes());
+    }
+
+    fn flush(&self) {
+        if let Some((ref mut file, _)) = *self.file.lock().unwrap_or_else(PoisonError::into_inner) {
+            let _ = file.flush();
+        }
+    }
+}
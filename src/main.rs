@@ -2,26 +2,15 @@
 
 use {
     std::{
-        cell::RefCell,
         collections::HashMap,
-        convert::identity,
-        rc::Rc,
+        fmt,
         sync::Arc,
         time::Duration,
     },
-    directories::BaseDirs,
-    itertools::Itertools as _,
+    log::LevelFilter,
     log_lock::*,
-    native_windows_derive as nwd,
-    native_windows_gui::{
-        self as nwg,
-        NativeUi as _,
-    },
-    open::that as open,
     serde::Deserialize,
-    smart_default::SmartDefault,
     tokio::{
-        io,
         runtime::{
             self,
             Runtime,
@@ -34,13 +23,8 @@ use {
         traits::{
             IsNetworkError,
             ReqwestResponseExt as _,
-            SendResultExt as _,
         },
     },
-    windows::UI::StartScreen::{
-        JumpList,
-        JumpListItem,
-    },
     crate::{
         config::Config,
         people::{
@@ -50,236 +34,65 @@ use {
     },
 };
 
+mod auth;
+mod command;
 mod config;
+mod daemon;
+mod dialog;
+mod discord;
+mod download;
+mod events;
+mod ferium;
 mod gui;
+mod hooks;
+mod ipc;
+mod java;
+mod jump_list;
 mod launcher;
+mod loader;
+mod logging;
+mod mods;
+mod mqtt;
+mod mrpack;
+mod native;
+mod packwiz;
 mod people;
+mod quick_play;
+#[cfg(test)] mod test_util;
+mod tray;
+mod version;
 
 const MAIN_WORLD: &str = "wurstmineberg";
 
 #[derive(Debug, Clone, Deserialize)]
 struct WorldStatus {
+    /// The address to join the world at, if reported by the API.
+    address: Option<String>,
+    /// The mod loader the world runs on, if reported by the API and known to this version of the app.
+    #[serde(default, deserialize_with = "loader::deserialize_known")]
+    loader: Option<loader::Loader>,
     #[serde(default)]
     list: Vec<Uid>,
     running: bool,
     version: Option<String>,
 }
 
-type State = (HashMap<Uid, Person>, HashMap<String, WorldStatus>);
-
-#[derive(SmartDefault, nwd::NwgUi)]
-pub struct SystemTray {
-    #[default(broadcast::Sender::new(1))]
-    gui_tx: broadcast::Sender<gui::Message>,
-    #[default(Runtime::new().ok())]
-    runtime: Option<Runtime>,
-    http_client: reqwest::Client,
-    config: Config,
-    state: Arc<Mutex<Option<Result<State, Arc<Error>>>>>,
-    #[nwg_control]
-    #[nwg_events(OnInit: [SystemTray::init])]
-    window: nwg::MessageWindow,
-    #[nwg_resource]
-    embed: nwg::EmbedResource,
-    #[nwg_control]
-    #[nwg_events(OnNotice: [SystemTray::set_icon])]
-    update_notice: nwg::Notice,
-    event_handler: RefCell<Option<nwg::EventHandler>>,
-    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_BLACK_16"))]
-    logo_black_16: nwg::Icon,
-    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_BLACK_32"))]
-    logo_black_32: nwg::Icon,
-    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_WHITE_16"))]
-    logo_white_16: nwg::Icon,
-    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_WHITE_32"))]
-    logo_white_32: nwg::Icon,
-    #[nwg_control(icon: Some(&data.logo_white_16), tip: Some("Wurstmineberg: Loading…"))]
-    #[nwg_events(MousePressLeftUp: [SystemTray::click], OnContextMenu: [SystemTray::show_menu(RC_SELF)])]
-    tray: nwg::TrayNotification,
-    tray_menu: RefCell<nwg::Menu>,
-    version_items: RefCell<Vec<(nwg::MenuItem, Option<String>)>>,
-    user_items: RefCell<Vec<(nwg::MenuItem, Uid)>>,
-    other_items: RefCell<Vec<nwg::MenuItem>>,
-    sep: RefCell<nwg::MenuSeparator>,
-    item_error: RefCell<nwg::MenuItem>,
-    item_launch_minecraft: RefCell<nwg::MenuItem>,
-    item_exit: RefCell<nwg::MenuItem>,
+/// The address to join when launching into the given world: the `serverAddresses` config entry if present, otherwise the address reported by the API, otherwise `wurstmineberg.de` for the main world.
+fn server_address(config: &Config, world: &str, status: Option<&WorldStatus>) -> Option<String> {
+    config.server_addresses.get(world).cloned()
+        .or_else(|| status.and_then(|status| status.address.clone()))
+        .or_else(|| (world == MAIN_WORLD).then(|| format!("wurstmineberg.de")))
 }
 
-impl SystemTray {
-    fn init(&self) {
-        self.set_icon();
-        self.runtime.as_ref().unwrap().spawn(maintain(self.http_client.clone(), self.state.clone(), self.update_notice.sender()));
-    }
-
-    fn set_icon(&self) {
-        let is_light = registry::Hive::CurrentUser.open(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize", registry::Security::QueryValue).ok()
-            .and_then(|key| key.value("SystemUsesLightTheme").ok())
-            .map_or(false, |data| matches!(data, registry::Data::U32(1)));
-        let (visibility, tooltip) = lock!(@blocking lock = self.state; match *lock {
-            Some(Ok((ref people, ref statuses))) => if statuses.values().any(|status| !status.list.is_empty()) || if statuses[MAIN_WORLD].running { self.config.show_if_empty } else { self.config.show_if_offline } {
-                (true, if let Ok((world_name, uid)) = statuses.iter().flat_map(|(world_name, world)| world.list.iter().map(move |uid| (world_name, uid))).exactly_one() {
-                    let person = people.get(uid).and_then(|person| person.name.clone()).unwrap_or_else(|| uid.to_string());
-                    format!("{person} is on {world_name}")
-                } else if let Ok((world_name, world)) = statuses.iter().filter(|(_, world)| !world.list.is_empty()).exactly_one() {
-                    format!("{} players are on {world_name}", world.list.len())
-                } else {
-                    format!("{} players are online", statuses.values().map(|world| world.list.len()).sum::<usize>())
-                })
-            } else {
-                (false, String::default())
-            },
-            Some(Err(_)) => (true, format!("error getting data")),
-            None => (true, format!("Wurstmineberg: Loading…")),
-        });
-        self.tray.set_visibility(visibility);
-        self.tray.set_icon(match (is_light, nwg::scale_factor() >= 1.5) {
-            (true, true) => &self.logo_black_32,
-            (true, false) => &self.logo_black_16,
-            (false, true) => &self.logo_white_32,
-            (false, false) => &self.logo_white_16,
-        });
-        self.tray.set_tip(&tooltip);
-    }
-
-    fn show_menu(self: &Rc<Self>) {
-        let (x, y) = nwg::GlobalCursor::position();
-        let mut menu = nwg::Menu::default();
-        nwg::Menu::builder()
-            .popup(true)
-            .parent(&self.window)
-            .build(&mut menu).expect("failed to generate tray menu");
-        self.user_items.borrow_mut().clear();
-        self.other_items.borrow_mut().clear();
-        let app = self.clone();
-        if let Some(previous_event_handler) = self.event_handler.replace(Some(nwg::full_bind_event_handler(&self.window.handle, move |event, _, handle| match event {
-            nwg::Event::OnMenuItemSelected => if handle == app.item_launch_minecraft.borrow().handle {
-                lock!(@blocking lock = app.state; app.gui_tx.send(gui::Message::LaunchMinecraft {
-                    config: Some(app.config.clone()),
-                    state: Some(lock.as_ref().expect("missing server state").clone()),
-                    menu: false,
-                    wait: false,
-                }).allow_unreceived());
-            } else if handle == app.item_exit.borrow().handle {
-                app.exit();
-            } else {
-                for (item, version) in &*app.version_items.borrow() {
-                    if handle == item.handle {
-                        if let Some(version) = version {
-                            open(format!("https://minecraft.wiki/w/Java_Edition_{version}")).expect("failed to open wiki article");
-                        }
-                        return
-                    }
-                }
-                for (item, uid) in &*app.user_items.borrow() {
-                    if handle == item.handle {
-                        open(format!("https://wurstmineberg.de/people/{uid}")).expect("failed to open user profile");
-                        return
-                    }
-                }
-            },
-            _ => {}
-        }))) {
-            nwg::unbind_event_handler(&previous_event_handler);
-        }
-        lock!(@blocking lock = self.state; match *lock {
-            Some(Ok((ref people, ref statuses))) => if statuses.values().any(|status| !status.list.is_empty()) || if statuses[MAIN_WORLD].running { self.config.show_if_empty } else { self.config.show_if_offline } {
-                for (world_name, status) in statuses {
-                    if (world_name == MAIN_WORLD && !status.running) || !status.list.is_empty() {
-                        let mut item = nwg::MenuItem::default();
-                        nwg::MenuItem::builder()
-                            .text(world_name)
-                            .disabled(true)
-                            .parent(&menu)
-                            .build(&mut item).expect("failed to generate tray menu");
-                        self.other_items.borrow_mut().push(item);
-                        //TODO respect versionLink config
-                        let mut item = nwg::MenuItem::default();
-                        if let Some(version) = &status.version {
-                            nwg::MenuItem::builder()
-                                .text(&format!("Version: {version}"))
-                                .parent(&menu)
-                                .build(&mut item).expect("failed to generate tray menu");
-                        } else {
-                            nwg::MenuItem::builder()
-                                .text("Modded Server, Unknown Version")
-                                .disabled(true)
-                                .parent(&menu)
-                                .build(&mut item).expect("failed to generate tray menu");
-                        }
-                        self.version_items.borrow_mut().push((item, status.version.clone()));
-                        if !status.running {
-                            let mut item = nwg::MenuItem::default();
-                            nwg::MenuItem::builder()
-                                .text("Server offline")
-                                .disabled(true)
-                                .parent(&menu)
-                                .build(&mut item).expect("failed to generate tray menu");
-                            self.other_items.borrow_mut().push(item);
-                        }
-                        for uid in &status.list {
-                            let mut item = nwg::MenuItem::default();
-                            nwg::MenuItem::builder()
-                                .text(&people.get(uid).and_then(|person| person.name.clone()).unwrap_or_else(|| uid.to_string()))
-                                .parent(&menu)
-                                .build(&mut item).expect("failed to generate tray menu");
-                            self.user_items.borrow_mut().push((item, uid.clone()));
-                        }
-                        nwg::MenuSeparator::builder()
-                            .parent(&menu)
-                            .build(&mut self.sep.borrow_mut()).expect("failed to generate tray menu");
-                    }
-                }
-            },
-            Some(Err(ref e)) => {
-                nwg::MenuItem::builder()
-                    .text(&e.to_string())
-                    .disabled(true)
-                    .parent(&menu)
-                    .build(&mut self.item_error.borrow_mut()).expect("failed to generate tray menu");
-                nwg::MenuSeparator::builder()
-                    .parent(&menu)
-                    .build(&mut self.sep.borrow_mut()).expect("failed to generate tray menu");
-            }
-            None => {}
-        });
-        nwg::MenuItem::builder()
-            .text("Start Minecraft")
-            .parent(&menu)
-            .build(&mut self.item_launch_minecraft.borrow_mut()).expect("failed to generate tray menu");
-        nwg::MenuItem::builder()
-            .text("Exit")
-            .parent(&menu)
-            .build(&mut self.item_exit.borrow_mut()).expect("failed to generate tray menu");
-        menu.popup(x, y);
-        *self.tray_menu.borrow_mut() = menu;
-    }
-
-    fn click(&self) {
-        if self.config.left_click_launch {
-            lock!(@blocking lock = self.state; self.gui_tx.send(gui::Message::LaunchMinecraft {
-                config: Some(self.config.clone()),
-                state: Some(lock.as_ref().expect("missing server state").clone()),
-                menu: false,
-                wait: false,
-            }).allow_unreceived());
-        }
-    }
-
-    fn exit(&self) {
-        self.gui_tx.send(gui::Message::Exit).allow_unreceived();
-        nwg::stop_thread_dispatch();
-    }
-}
+type State = (HashMap<Uid, Person>, HashMap<String, WorldStatus>);
 
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error(transparent)] Config(#[from] config::Error),
     #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Launcher(#[from] launcher::Error),
     #[error(transparent)] Reqwest(#[from] reqwest::Error),
     #[error(transparent)] Wheel(#[from] wheel::Error),
-    #[error("failed to find user folder")]
-    BaseDirs,
     #[error("no profile named “{0}” in launcher data")]
     UnknownLauncherProfile(String),
 }
@@ -289,15 +102,16 @@ impl IsNetworkError for Error {
         match self {
             Self::Config(_) => false,
             Self::Json(_) => false,
+            Self::Launcher(_) => false,
             Self::Reqwest(e) => e.is_network_error(),
             Self::Wheel(e) => e.is_network_error(),
-            Self::BaseDirs => false,
             Self::UnknownLauncherProfile(_) => false,
         }
     }
 }
 
 async fn get_state(http_client: &reqwest::Client) -> Result<State, Error> {
+    log::debug!("getting server state");
     let people = http_client.get("https://wurstmineberg.de/api/v3/people.json")
         .send().await?
         .detailed_error_for_status().await?
@@ -307,10 +121,14 @@ async fn get_state(http_client: &reqwest::Client) -> Result<State, Error> {
         .send().await?
         .detailed_error_for_status().await?
         .json_with_text_in_error().await?;
+    log::debug!("got server state: {} people, {} worlds", people.len(), statuses.len());
     Ok((people, statuses))
 }
 
-async fn maintain_inner(http_client: &reqwest::Client, state: Arc<Mutex<Option<Result<State, Arc<Error>>>>>, update_notifier: nwg::NoticeSender) -> Result<(), Error> {
+async fn maintain_inner(http_client: &reqwest::Client, state: Arc<Mutex<tray::SharedState>>, on_update: &mut (impl FnMut(&tray::SharedState) + Send)) -> Result<(), Error> {
+    let hooks_client = hooks::client()?;
+    let mut previous_state = None;
+    let mut mqtt_publisher = None::<mqtt::Publisher>;
     loop {
         let config = Config::load().await?; //TODO update config field of app? (make sure to keep overrides from CLI args)
         let new_state = match get_state(&http_client).await {
@@ -319,17 +137,15 @@ async fn maintain_inner(http_client: &reqwest::Client, state: Arc<Mutex<Option<R
                     status.list.retain(|uid| !config.ignored_players.contains(uid));
                 }
                 if !config.version_match.is_empty() {
-                    let base_dirs = BaseDirs::new().ok_or(Error::BaseDirs)?;
-                    let mut launcher_data_path = base_dirs.data_dir().join(".minecraft").join("launcher_profiles_microsoft_store.json");
-                    if !fs::exists(&launcher_data_path).await? {
-                        launcher_data_path = base_dirs.data_dir().join(".minecraft").join("launcher_profiles.json");
-                    }
+                    let minecraft_dir = launcher::SearchRoots::new()?.minecraft_dir(&config).await?.ok_or(launcher::Error::MinecraftDir)?;
+                    let launcher_data_path = launcher::data_path(&minecraft_dir).await?;
                     let mut launcher_data = fs::read_json::<launcher::Data>(&launcher_data_path).await?;
                     let mut modified = false;
                     for (profile_id, world_name) in &config.version_match {
                         let launcher_profile = launcher_data.profiles.get_mut(profile_id).ok_or_else(|| Error::UnknownLauncherProfile(profile_id.clone()))?;
                         if let Some(world_version) = &statuses[world_name].version {
                             if launcher_profile.last_version_id != *world_version {
+                                log::info!("updating launcher profile {profile_id} from version {} to {world_version}", launcher_profile.last_version_id);
                                 launcher_profile.last_version_id = world_version.clone();
                                 modified = true;
                             }
@@ -343,48 +159,58 @@ async fn maintain_inner(http_client: &reqwest::Client, state: Arc<Mutex<Option<R
                 }
                 Ok((people, statuses))
             }
-            Err(e) if e.is_network_error() => Err(e),
+            Err(e) if e.is_network_error() => {
+                log::warn!("network error getting server state: {e}");
+                Err(e)
+            }
             Err(e) => return Err(e),
         };
-        lock!(state = state; *state = Some(new_state.map_err(Arc::new)));
-        update_notifier.notice();
+        match new_state {
+            Ok(ref new_state) => {
+                // no events for the initial state or after errors, since what changed is unknown
+                let events = previous_state.replace(new_state.clone()).map(|previous_state| events::diff(&previous_state, new_state)).unwrap_or_default();
+                hooks::run(&hooks_client, &config.hooks, &events);
+                if mqtt_publisher.as_ref().map(mqtt::Publisher::config) != config.mqtt.as_ref() {
+                    mqtt_publisher = config.mqtt.clone().map(mqtt::Publisher::new);
+                }
+                if let Some(ref mut mqtt_publisher) = mqtt_publisher {
+                    mqtt_publisher.update(new_state, &events);
+                }
+            }
+            Err(_) => previous_state = None,
+        }
+        let new_state = Some(new_state.map_err(Arc::new));
+        lock!(state = state; *state = new_state.clone());
+        on_update(&new_state);
         sleep(Duration::from_secs(45)).await;
     }
 }
 
-async fn maintain(http_client: reqwest::Client, state: Arc<Mutex<Option<Result<State, Arc<Error>>>>>, update_notifier: nwg::NoticeSender) {
-    if let Err(e) = maintain_inner(&http_client, state, update_notifier).await {
-        nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = maintain, {e:?}"))
+/// Periodically updates the server state, calling `on_update` with the new state after each update.
+async fn maintain(http_client: reqwest::Client, state: Arc<Mutex<tray::SharedState>>, mut on_update: impl FnMut(&tray::SharedState) + Send + 'static) {
+    if let Err(e) = maintain_inner(&http_client, state, &mut on_update).await {
+        fatal("maintain", e)
     }
 }
 
-async fn configure_jump_list() -> Result<(), windows::core::Error> {
-    let list = JumpList::LoadCurrentAsync()?.await?;
-    let items = list.Items()?;
-    items.Clear()?;
-    let main_menu_item = JumpListItem::CreateWithArguments(&"launch --menu".into(), &"Minecraft Main Menu".into())?;
-    //TODO main_menu_item.SetLogo (how to refer to an embedded resource?)
-    items.Append(&main_menu_item)?;
-    list.SaveAsync()?.await?;
-    Ok(())
-}
-
 #[derive(clap::Parser)]
 struct Args {
     #[clap(long)]
     show_if_empty: bool,
+    /// The maximum level of messages written to the log file, overriding the `logLevel` config entry.
+    #[clap(long)]
+    log_level: Option<LevelFilter>,
     #[clap(subcommand)]
     subcommand: Option<Subcommand>,
 }
 
 impl Args {
-    fn to_config(self) -> Result<Config, config::Error> {
-        let Self { show_if_empty, subcommand: _ } = self;
-        let mut config = Config::blocking_load()?;
-        if show_if_empty {
+    /// Applies the command-line options that override config entries.
+    fn apply(&self, mut config: Config) -> Config {
+        if self.show_if_empty {
             config.show_if_empty = true;
         }
-        Ok(config)
+        config
     }
 }
 
@@ -396,45 +222,66 @@ enum Subcommand {
         menu: bool,
         #[clap(long)]
         no_wait: bool,
+        /// The Wurstmineberg world to launch into. Defaults to the main world.
+        #[clap(long, default_value = MAIN_WORLD)]
+        world: String,
     },
+    /// Keep the server state up to date without showing a tray icon, and serve it to other programs via a local HTTP API.
+    Daemon,
+    /// Exit the running instance of the app.
+    Exit,
+    /// Open the Wurstmineberg map in the browser.
+    OpenMap,
+    /// Open the config file, creating it if necessary.
+    OpenSettings,
+    /// Open the folder containing the app's log files.
+    OpenLogFolder,
+    /// Sign in with a Microsoft account for the built-in launcher.
+    SignIn,
+    /// Remove the stored Microsoft account.
+    SignOut,
 }
 
-#[derive(Debug, thiserror::Error)]
-enum GuiMainError {
-    #[error(transparent)] Config(#[from] config::Error),
-    #[error(transparent)] Io(#[from] io::Error),
-    #[error(transparent)] Nwg(#[from] nwg::NwgError),
+/// Sends the command to the running instance of the app, if any. Returns `false` if the command should be handled by this process instead.
+fn forward(runtime: &Runtime, command: ipc::Command) -> bool {
+    match runtime.block_on(ipc::forward(&command)) {
+        Ok(forwarded) => forwarded,
+        Err(e) => {
+            log::warn!("failed to forward command to running instance: {e} ({e:?})");
+            false
+        }
+    }
 }
 
-fn gui_main(runtime: Runtime, http_client: reqwest::Client, args: Args, gui_tx: broadcast::Sender<gui::Message>) -> Result<(), GuiMainError> {
-    nwg::init()?;
-    let app = SystemTray::build_ui(SystemTray {
-        runtime: Some(runtime),
-        config: args.to_config()?,
-        gui_tx, http_client,
-        ..SystemTray::default()
-    })?;
-    nwg::dispatch_thread_events();
-    drop(app);
-    Ok(())
+/// Logs the error and shows it in a dialog, then exits. `ctx` tells apart the places where fatal errors can happen.
+pub(crate) fn fatal(ctx: &str, e: impl fmt::Display + fmt::Debug) -> ! {
+    log::error!("fatal error (ctx = {ctx}): {e} ({e:?})");
+    dialog::fatal(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = {ctx}, {e:?}"))
 }
 
 #[wheel::main]
 fn main(args: Args) {
+    // errors are only reported once it's known whether the config is needed, but the log level is needed first
+    let config = Config::blocking_load().map(|config| args.apply(config));
+    let log_level = args.log_level.or_else(|| config.as_ref().ok().and_then(|config| config.log_level)).unwrap_or(LevelFilter::Info);
+    if let Err(e) = logging::init(log_level) {
+        dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to set up logging"), &format!("{e}\nDebug info: {e:?}"));
+    }
+    log::info!("starting {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
     let default_panic_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
-        nwg::error_message("Wurstmineberg: thread panic", &format!("Debug info: {info:?}"));
+        log::error!("thread panic: {info}");
+        dialog::error("Wurstmineberg: thread panic", &format!("Debug info: {info:?}"));
         default_panic_hook(info)
     }));
     let _ = rustls::crypto::ring::default_provider().install_default();
     let runtime = match runtime::Builder::new_multi_thread().enable_all().build() {
         Ok(runtime) => runtime,
-        Err(e) => nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = runtime, {e:?}")),
+        Err(e) => fatal("runtime", e),
     };
-    if JumpList::IsSupported().is_ok_and(identity) {
-        if let Err(e) = runtime.block_on(configure_jump_list()) {
-            nwg::error_message(concat!(env!("CARGO_PKG_NAME"), ": failed to configure jump list"), &format!("{e}\nDebug info: {e:?}"));
-        }
+    #[cfg(windows)] if let Err(e) = runtime.block_on(jump_list::configure(&jump_list::tasks(&None))) {
+        log::warn!("failed to configure jump list: {e}");
+        dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to configure jump list"), &format!("{e}\nDebug info: {e:?}"));
     }
     let http_client = match reqwest::Client::builder()
         .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " (", env!("CARGO_PKG_REPOSITORY"), ")"))
@@ -445,21 +292,75 @@ fn main(args: Args) {
         .build()
     {
         Ok(http_client) => http_client,
-        Err(e) => nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = http_client, {e:?}")),
+        Err(e) => fatal("http_client", e),
     };
     match args.subcommand {
         None => {
+            // binding first means two instances started at the same time can't both end up showing a tray icon
+            let listener = match runtime.block_on(async { ipc::listen() }) {
+                Ok(listener) => Some(listener),
+                Err(e) => if forward(&runtime, ipc::Command::ShowMenu) {
+                    log::info!("another instance is already running, exiting");
+                    return
+                } else {
+                    log::warn!("failed to listen for commands from other instances: {e} ({e:?})");
+                    None
+                },
+            };
+            let config = config.unwrap_or_else(|e| fatal("config", e));
             let (tx, rx) = broadcast::channel(32);
-            let tray_http_client = http_client.clone();
-            std::thread::spawn(move || if let Err(e) = gui_main(runtime, tray_http_client, args, tx) {
-                nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = main, {e:?}"))
+            let context = tray::Context {
+                gui_tx: tx,
+                http_client: http_client.clone(),
+                config,
+                ..tray::Context::default()
+            };
+            if let Some(listener) = listener {
+                runtime.spawn(ipc::serve(listener, context.clone()));
+            }
+            std::thread::spawn(move || if let Err(e) = tray::run(runtime, context) {
+                fatal("main", e)
             });
             if let Err(e) = gui::run(http_client, gui::Args::Default { rx }) {
-                nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = gui, {e:?}"))
+                fatal("gui", e)
+            }
+        }
+        Some(Subcommand::Launch { menu, no_wait, world }) => if !forward(&runtime, ipc::Command::Launch { menu, wait: !no_wait, world: world.clone() }) {
+            if let Err(e) = gui::run(http_client, gui::Args::Launch { menu, wait: !no_wait, world }) {
+                fatal("gui", e)
+            }
+        },
+        Some(Subcommand::Daemon) => {
+            let config = config.unwrap_or_else(|e| fatal("config", e));
+            if let Err(e) = runtime.block_on(daemon::run(http_client, config)) {
+                fatal("daemon", e)
             }
         }
-        Some(Subcommand::Launch { menu, no_wait }) => if let Err(e) = gui::run(http_client, gui::Args::Launch { menu, wait: !no_wait }) {
-            nwg::fatal_message(concat!(env!("CARGO_PKG_NAME"), ": fatal error"), &format!("{e}\nDebug info: ctx = gui, {e:?}"))
+        Some(Subcommand::Exit) => if !forward(&runtime, ipc::Command::Exit) {
+            log::info!("no running instance to exit");
+        },
+        Some(Subcommand::OpenMap) => if let Err(e) = open::that(jump_list::MAP_URL) {
+            log::error!("failed to open map: {e} ({e:?})");
+            dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to open map"), &format!("{e}\nDebug info: {e:?}"));
+        },
+        Some(Subcommand::OpenSettings) => if !forward(&runtime, ipc::Command::OpenSettings) {
+            if let Err(e) = config::open_file() {
+                log::error!("failed to open config file: {e} ({e:?})");
+                dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to open config file"), &format!("{e}\nDebug info: {e:?}"));
+            }
+        },
+        Some(Subcommand::OpenLogFolder) => if let Err(e) = logging::open_dir() {
+            log::error!("failed to open log folder: {e} ({e:?})");
+            dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to open log folder"), &format!("{e}\nDebug info: {e:?}"));
+        },
+        Some(Subcommand::SignIn) => if !forward(&runtime, ipc::Command::SignIn) {
+            if let Err(e) = gui::run(http_client, gui::Args::SignIn) {
+                fatal("gui", e)
+            }
+        },
+        Some(Subcommand::SignOut) => if let Err(e) = runtime.block_on(auth::sign_out()) {
+            log::error!("failed to sign out: {e} ({e:?})");
+            dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to sign out"), &format!("{e}\nDebug info: {e:?}"));
         },
     }
 }
@@ -0,0 +1,88 @@
+//! Comparing the Fabric mods installed in a game folder against the mods a world requires.
+
+use {
+    std::{
+        collections::BTreeMap,
+        io::prelude::*,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    serde::Deserialize,
+    wheel::traits::IoResultExt as _,
+    zip::ZipArchive,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+}
+
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    version: String,
+}
+
+/// Reads the ID and version of each Fabric mod in the `mods` subfolder of `game_dir`.
+///
+/// Files that aren't Fabric mods are skipped.
+pub(crate) async fn installed(game_dir: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let mods_dir = game_dir.join("mods");
+    Ok(tokio::task::spawn_blocking(move || {
+        let mut mods = BTreeMap::default();
+        let entries = match std::fs::read_dir(&mods_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(mods),
+            Err(e) => return Err(e).at(&mods_dir),
+        };
+        for entry in entries {
+            let path = entry.at(&mods_dir)?.path();
+            if path.extension().is_some_and(|ext| ext == "jar") && let Some(mod_json) = read_mod_json(path) {
+                mods.insert(mod_json.id, mod_json.version);
+            }
+        }
+        Ok::<_, wheel::Error>(mods)
+    }).await??)
+}
+
+fn read_mod_json(jar_path: PathBuf) -> Option<FabricModJson> {
+    let mut zip = ZipArchive::new(std::fs::File::open(jar_path).ok()?).ok()?;
+    let mut buf = String::default();
+    zip.by_name("fabric.mod.json").ok()?.read_to_string(&mut buf).ok()?;
+    serde_json::from_str(&buf).ok()
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Report {
+    /// Required mods that aren't installed, with the required version.
+    pub(crate) missing: Vec<(String, String)>,
+    /// Installed mods with a different version than required, with the installed and required versions.
+    pub(crate) outdated: Vec<(String, String, String)>,
+    /// Installed mods that aren't required, with the installed version.
+    pub(crate) extra: Vec<(String, String)>,
+}
+
+impl Report {
+    /// Compares installed mods against required ones. A required version of `*` accepts any installed version.
+    pub(crate) fn new(mut installed: BTreeMap<String, String>, required: &BTreeMap<String, String>) -> Self {
+        let mut report = Self::default();
+        for (id, required_version) in required {
+            match installed.remove(id) {
+                Some(installed_version) => if required_version != "*" && installed_version != *required_version {
+                    report.outdated.push((id.clone(), installed_version, required_version.clone()));
+                },
+                None => report.missing.push((id.clone(), required_version.clone())),
+            }
+        }
+        report.extra = installed.into_iter().collect();
+        report
+    }
+
+    /// Whether joining the world is expected to work. Extra mods are assumed to be client-side only.
+    pub(crate) fn is_compatible(&self) -> bool {
+        self.missing.is_empty() && self.outdated.is_empty()
+    }
+}
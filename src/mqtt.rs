@@ -0,0 +1,309 @@
+//! Publishing the server state to an MQTT broker, e.g. for home automation.
+//!
+//! Topics, relative to the configured prefix:
+//!
+//! * `status`: `online` while the app is connected, `offline` otherwise (retained)
+//! * `<world>/state`: the world's status as JSON (retained)
+//! * `<world>/events`: events like players joining, as JSON
+//!
+//! Home Assistant discovery messages are also sent so each world shows up as a device with sensors.
+
+use {
+    std::{
+        collections::HashSet,
+        time::Duration,
+    },
+    rumqttc::{
+        AsyncClient,
+        LastWill,
+        MqttOptions,
+        QoS,
+    },
+    serde::Serialize,
+    tokio::{
+        task::JoinHandle,
+        time::sleep,
+    },
+    crate::{
+        State,
+        config,
+        events::Event,
+    },
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorldState<'a> {
+    running: bool,
+    version: Option<&'a str>,
+    player_count: usize,
+    /// Display names, or Wurstmineberg IDs or Discord snowflakes for players without one.
+    players: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Discovery<'a> {
+    name: &'a str,
+    unique_id: String,
+    state_topic: &'a str,
+    value_template: &'a str,
+    availability_topic: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_attributes_topic: Option<&'a str>,
+    device: Device<'a>,
+}
+
+#[derive(Serialize)]
+struct Device<'a> {
+    identifiers: [&'a str; 1],
+    name: String,
+    manufacturer: &'static str,
+}
+
+/// Home Assistant object IDs may only contain alphanumeric characters, underscores, and hyphens.
+fn object_id(world: &str) -> String {
+    let sanitized = world.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect::<String>();
+    format!("wurstmineberg_{sanitized}")
+}
+
+pub(crate) struct Publisher {
+    config: config::Mqtt,
+    client: AsyncClient,
+    event_loop: JoinHandle<()>,
+    /// Worlds for which discovery messages have already been sent.
+    discovered: HashSet<String>,
+}
+
+impl Publisher {
+    /// Connects to the broker in the background, reconnecting as needed until the publisher is dropped.
+    pub(crate) fn new(config: config::Mqtt) -> Self {
+        let mut options = MqttOptions::new(format!("{}-{}", env!("CARGO_PKG_NAME"), std::process::id()), &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(60));
+        options.set_last_will(LastWill::new(format!("{}/status", config.topic_prefix), "offline", QoS::AtLeastOnce, true));
+        if let Some(ref username) = config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        let (client, mut connection) = AsyncClient::new(options, 64);
+        let host = config.host.clone();
+        let event_loop = tokio::spawn(async move {
+            loop {
+                match connection.poll().await {
+                    Ok(event) => log::trace!("MQTT event: {event:?}"),
+                    Err(e) => {
+                        log::warn!("MQTT connection to {host} failed: {e}");
+                        sleep(Duration::from_secs(10)).await;
+                    }
+                }
+            }
+        });
+        Self { config, client, event_loop, discovered: HashSet::default() }
+    }
+
+    pub(crate) fn config(&self) -> &config::Mqtt {
+        &self.config
+    }
+
+    /// Queues a message without waiting for it to be sent, so an unreachable broker doesn't hold up updating the server state.
+    ///
+    /// Returns whether the message was queued.
+    fn publish(&self, topic: String, retain: bool, payload: String) -> bool {
+        match self.client.try_publish(&topic, QoS::AtLeastOnce, retain, payload) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("failed to publish to MQTT topic {topic}: {e}");
+                false
+            }
+        }
+    }
+
+    fn publish_json(&self, topic: String, retain: bool, payload: &impl Serialize) -> bool {
+        match serde_json::to_string_pretty(payload) {
+            Ok(payload) => self.publish(topic, retain, payload),
+            Err(e) => {
+                log::error!("failed to serialize MQTT message for topic {topic}: {e}");
+                false
+            }
+        }
+    }
+
+    fn discover(&mut self, world: &str) {
+        let Some(ref discovery_prefix) = self.config.discovery_prefix else { return };
+        if self.discovered.contains(world) { return }
+        let object_id = object_id(world);
+        let state_topic = format!("{}/{world}/state", self.config.topic_prefix);
+        let availability_topic = format!("{}/status", self.config.topic_prefix);
+        let device = || Device {
+            identifiers: [object_id.as_str()],
+            name: format!("Wurstmineberg {world}"),
+            manufacturer: "Wurstmineberg",
+        };
+        let players = self.publish_json(format!("{discovery_prefix}/sensor/{object_id}/players/config"), true, &Discovery {
+            name: "Players online",
+            unique_id: format!("{object_id}_players"),
+            state_topic: &state_topic,
+            value_template: "{{ value_json.playerCount }}",
+            availability_topic: &availability_topic,
+            device_class: None,
+            json_attributes_topic: Some(&state_topic),
+            device: device(),
+        });
+        let version = self.publish_json(format!("{discovery_prefix}/sensor/{object_id}/version/config"), true, &Discovery {
+            name: "Version",
+            unique_id: format!("{object_id}_version"),
+            state_topic: &state_topic,
+            value_template: "{{ value_json.version }}",
+            availability_topic: &availability_topic,
+            device_class: None,
+            json_attributes_topic: None,
+            device: device(),
+        });
+        let running = self.publish_json(format!("{discovery_prefix}/binary_sensor/{object_id}/running/config"), true, &Discovery {
+            name: "Running",
+            unique_id: format!("{object_id}_running"),
+            state_topic: &state_topic,
+            value_template: "{{ 'ON' if value_json.running else 'OFF' }}",
+            availability_topic: &availability_topic,
+            device_class: Some("running"),
+            json_attributes_topic: None,
+            device: device(),
+        });
+        // otherwise, discovery is retried on the next update
+        if players && version && running {
+            self.discovered.insert(world.to_owned());
+        }
+    }
+
+    /// Publishes the new state of each world and the events since the previous state.
+    pub(crate) fn update(&mut self, (people, statuses): &State, events: &[Event]) {
+        let prefix = self.config.topic_prefix.clone();
+        self.publish(format!("{prefix}/status"), true, format!("online"));
+        for (world, status) in statuses {
+            self.discover(world);
+            self.publish_json(format!("{prefix}/{world}/state"), true, &WorldState {
+                running: status.running,
+                version: status.version.as_deref(),
+                player_count: status.list.len(),
+                players: status.list.iter().map(|uid| people.get(uid).and_then(|person| person.name.clone()).unwrap_or_else(|| uid.to_string())).collect(),
+            });
+        }
+        for event in events {
+            self.publish_json(format!("{prefix}/{}/events", event.world()), false, event);
+        }
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        // the event loop keeps its own sender for requests, so it doesn't stop by itself
+        self.event_loop.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::collections::HashMap,
+        tokio::{
+            io::{
+                AsyncReadExt as _,
+                AsyncWriteExt as _,
+            },
+            net::{
+                TcpListener,
+                TcpStream,
+            },
+            time::timeout,
+        },
+        crate::{
+            WorldStatus,
+            events::Player,
+            people::Uid,
+        },
+        super::*,
+    };
+
+    fn config(port: u16, discovery_prefix: &str) -> config::Mqtt {
+        config::Mqtt {
+            host: format!("127.0.0.1"),
+            username: None,
+            password: None,
+            topic_prefix: format!("wurstmineberg"),
+            discovery_prefix: Some(discovery_prefix.to_owned()),
+            port,
+        }
+    }
+
+    fn state() -> State {
+        let status = WorldStatus { address: None, loader: None, list: vec![Uid::WmbId(format!("fenhl"))], running: true, version: Some(format!("1.21.4")) };
+        (HashMap::default(), HashMap::from([(format!("wurstmineberg"), status)]))
+    }
+
+    /// Reads an MQTT packet, returning the packet type and flags byte and the rest of the packet.
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let mut len = 0;
+        for shift in (0..4).map(|i| i * 7) {
+            let byte = stream.read_u8().await.unwrap();
+            len |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 { break }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    /// Reads a length-prefixed string from an MQTT packet body.
+    fn read_string(body: &[u8]) -> (&str, &[u8]) {
+        let len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+        (std::str::from_utf8(&body[2..2 + len]).unwrap(), &body[2 + len..])
+    }
+
+    #[tokio::test]
+    async fn publish_to_broker() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let mut publisher = Publisher::new(config(listener.local_addr().unwrap().port(), "homeassistant"));
+        let events = [Event::Join { world: format!("wurstmineberg"), player: Player { uid: format!("fenhl"), name: format!("fenhl") } }];
+        publisher.update(&state(), &events);
+        assert!(publisher.discovered.contains("wurstmineberg"));
+        let topics = timeout(Duration::from_secs(10), async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x10, "expected CONNECT packet");
+            let (protocol, rest) = read_string(&body);
+            assert_eq!(protocol, "MQTT");
+            let flags = rest[1];
+            assert_eq!(flags & 0x24, 0x24, "expected a retained last will");
+            let (client_id, rest) = read_string(&rest[4..]);
+            assert!(client_id.starts_with(env!("CARGO_PKG_NAME")));
+            let (will_topic, _) = read_string(rest);
+            assert_eq!(will_topic, "wurstmineberg/status");
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap(); // CONNACK
+            let mut topics = Vec::default();
+            while topics.len() < 6 {
+                let (header, body) = read_packet(&mut stream).await;
+                if header >> 4 == 3 {
+                    topics.push(read_string(&body).0.to_owned());
+                }
+            }
+            topics
+        }).await.expect("no messages received");
+        assert_eq!(topics, [
+            "wurstmineberg/status",
+            "homeassistant/sensor/wurstmineberg_wurstmineberg/players/config",
+            "homeassistant/sensor/wurstmineberg_wurstmineberg/version/config",
+            "homeassistant/binary_sensor/wurstmineberg_wurstmineberg/running/config",
+            "wurstmineberg/wurstmineberg/state",
+            "wurstmineberg/wurstmineberg/events",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn discovery_retried_after_failure() {
+        // wildcards aren't allowed in topics, so the discovery messages can't be queued
+        let mut publisher = Publisher::new(config(1, "home#assistant"));
+        publisher.update(&state(), &[]);
+        assert!(publisher.discovered.is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! Installing Modrinth modpacks (`.mrpack` files, see <https://support.modrinth.com/en/articles/8802351-modrinth-modpack-format-mrpack>).
+
+use {
+    std::{
+        collections::{
+            BTreeMap,
+            BTreeSet,
+        },
+        io::{
+            Cursor,
+            prelude::*,
+        },
+        path::Path,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    wheel::{
+        fs,
+        traits::IoResultExt as _,
+    },
+    tokio::sync::watch,
+    zip::ZipArchive,
+    crate::download::{
+        self,
+        HashFormat,
+        Location,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Download(#[from] download::Error),
+    #[error(transparent)] Io(#[from] std::io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error(transparent)] Zip(#[from] zip::result::ZipError),
+    #[error("all downloads of {0} failed")]
+    AllDownloadsFailed(String, #[source] download::Error),
+    #[error("modpack file {0} has no download URLs")]
+    NoDownloads(String),
+    #[error("unsupported modpack format version: {0}")]
+    FormatVersion(u32),
+}
+
+/// The hashes of the override files as of the last install, so that files edited by the user are only replaced if the pack changes them.
+#[derive(Default, Deserialize, Serialize)]
+struct OverrideHashes {
+    sha512: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Index {
+    format_version: u32,
+    #[serde(default)]
+    files: Vec<File>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct File {
+    path: String,
+    hashes: Hashes,
+    env: Option<Env>,
+    downloads: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Hashes {
+    sha512: String,
+}
+
+#[derive(Deserialize)]
+struct Env {
+    client: EnvSupport,
+}
+
+#[derive(PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EnvSupport {
+    Required,
+    Optional,
+    Unsupported,
+}
+
+/// Installs or updates the modpack at `pack` into `instance_dir`, applying its client overrides and removing files that were previously installed from it but are no longer part of it.
+///
+/// Override files are only written if they're missing or the pack has changed them since the last install, so changes the user made to e.g. config files are kept.
+///
+/// `progress` is called with the number of files downloaded so far and the total number of files. If `cancel` is set, this stops between files with [`download::Error::Cancelled`].
+///
+/// Returns the pack's dependencies, e.g. `minecraft` and `fabric-loader`, mapped to the required versions.
+pub(crate) async fn install(http_client: &reqwest::Client, pack: &str, instance_dir: &Path, cancel: &watch::Receiver<bool>, mut progress: impl FnMut(usize, usize)) -> Result<BTreeMap<String, String>, Error> {
+    let data = Location::parse(pack)?.read(http_client).await?;
+    let (index, overrides) = tokio::task::spawn_blocking(move || read_pack(data)).await??;
+    if index.format_version != 1 { return Err(Error::FormatVersion(index.format_version)) }
+    let mut installed = BTreeSet::default();
+    let total = index.files.len();
+    for (done, file) in index.files.into_iter().enumerate() {
+        download::check_cancelled(cancel)?;
+        progress(done, total);
+        if file.env.is_some_and(|env| env.client == EnvSupport::Unsupported) { continue }
+        if file.downloads.is_empty() {
+            return Err(Error::NoDownloads(file.path))
+        }
+        let path = instance_dir.join(download::relative_path(&file.path)?);
+        let mut last_error = None;
+        for url in &file.downloads {
+            // the format only allows HTTPS downloads, and a remote pack mustn't be able to install local files
+            let result = match Location::parse_https(url) {
+                Ok(location) => download::install_verified(http_client, &location, &path, HashFormat::Sha512, &file.hashes.sha512).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => {
+                    last_error = None;
+                    break
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if let Some(e) = last_error {
+            return Err(Error::AllDownloadsFailed(file.path, e))
+        }
+        installed.insert(file.path);
+    }
+    progress(total, total);
+    let override_hashes_path = instance_dir.join(".wurstmineberg-mrpack-overrides.json");
+    let previous_hashes = fs::read_json::<OverrideHashes>(&override_hashes_path).await.missing_ok()?;
+    let mut hashes = OverrideHashes::default();
+    // later entries replace earlier ones so client overrides take precedence
+    for (relative, contents) in overrides.into_iter().collect::<BTreeMap<_, _>>() {
+        download::check_cancelled(cancel)?;
+        let path = instance_dir.join(download::relative_path(&relative)?);
+        let hash = HashFormat::Sha512.hash(&contents);
+        if previous_hashes.sha512.get(&relative) != Some(&hash) || !fs::exists(&path).await? {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(path, contents).await?;
+        }
+        hashes.sha512.insert(relative.clone(), hash);
+        installed.insert(relative);
+    }
+    let mut buf = serde_json::to_string_pretty(&hashes)?;
+    buf.push('\n');
+    fs::create_dir_all(instance_dir).await?;
+    fs::write(override_hashes_path, buf).await?;
+    download::replace_installed(instance_dir, ".wurstmineberg-mrpack.json", installed).await?;
+    Ok(index.dependencies)
+}
+
+/// Reads the index and the contents of `overrides` and `client-overrides` from the modpack file, with paths relative to the override folders.
+///
+/// Files in `client-overrides` come after those in `overrides` so they take precedence.
+fn read_pack(data: Vec<u8>) -> Result<(Index, Vec<(String, Vec<u8>)>), Error> {
+    let mut zip = ZipArchive::new(Cursor::new(data))?;
+    let mut index = String::default();
+    zip.by_name("modrinth.index.json")?.read_to_string(&mut index)?;
+    let index = serde_json::from_str(&index)?;
+    let mut overrides = Vec::default();
+    let mut client_overrides = Vec::default();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if file.is_dir() { continue }
+        let (target, relative) = if let Some(relative) = file.name().strip_prefix("overrides/") {
+            (&mut overrides, relative.to_owned())
+        } else if let Some(relative) = file.name().strip_prefix("client-overrides/") {
+            (&mut client_overrides, relative.to_owned())
+        } else {
+            continue
+        };
+        let mut contents = Vec::default();
+        file.read_to_end(&mut contents)?;
+        target.push((relative, contents));
+    }
+    overrides.extend(client_overrides);
+    Ok((index, overrides))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::fs,
+        zip::{
+            CompressionMethod,
+            ZipWriter,
+            write::SimpleFileOptions,
+        },
+        axum::{
+            Router,
+            routing::get,
+        },
+        crate::test_util,
+        super::*,
+    };
+
+    /// Builds a `.mrpack` file with the given index and files in `dir` and returns its path.
+    fn write_pack(dir: &Path, index: &str, files: &[(&str, &str)]) -> String {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::default()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("modrinth.index.json", options).unwrap();
+        zip.write_all(index.as_bytes()).unwrap();
+        for &(name, contents) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let path = dir.join("pack.mrpack");
+        fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        path.display().to_string()
+    }
+
+    fn index(files: &[(&str, &str, &[String])]) -> String {
+        let files = files.iter().map(|&(path, contents, downloads)| format!(
+            r#"{{"path": "{path}", "hashes": {{"sha1": "", "sha512": "{}"}}, "downloads": [{}], "fileSize": {}}}"#,
+            HashFormat::Sha512.hash(contents.as_bytes()),
+            downloads.iter().map(|url| format!("{url:?}")).collect::<Vec<_>>().join(", "),
+            contents.len(),
+        )).collect::<Vec<_>>().join(", ");
+        format!(r#"{{"formatVersion": 1, "game": "minecraft", "versionId": "1", "name": "Test", "files": [{files}], "dependencies": {{"minecraft": "1.21.1", "fabric-loader": "0.16.5"}}}}"#)
+    }
+
+    #[tokio::test]
+    async fn install_local_pack() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("mrpack-install");
+        let instance_dir = dir.join("instance");
+        let base_url = test_util::serve(Router::new().route("/sodium.jar", get(|| async { "sodium" }))).await;
+        // the first download is an invalid URL, which should fall back to the second one
+        let downloads = [format!("https://[invalid"), format!("{base_url}/sodium.jar")];
+        let pack = write_pack(&dir, &index(&[("mods/sodium.jar", "sodium", &downloads[..])]), &[
+            ("overrides/config/options.txt", "renderDistance: 12"),
+            ("overrides/config/shared.txt", "common"),
+            ("client-overrides/config/shared.txt", "client"),
+            ("server-overrides/config/server.txt", "server"),
+        ]);
+        let http_client = test_util::http_client();
+        let dependencies = install(&http_client, &pack, &instance_dir, &cancel, |_, _| {}).await.unwrap();
+        assert_eq!(dependencies.get("minecraft").map(String::as_str), Some("1.21.1"));
+        assert_eq!(dependencies.get("fabric-loader").map(String::as_str), Some("0.16.5"));
+        let config_dir = instance_dir.join("config");
+        assert_eq!(fs::read_to_string(instance_dir.join("mods").join("sodium.jar")).unwrap(), "sodium");
+        assert_eq!(fs::read_to_string(config_dir.join("options.txt")).unwrap(), "renderDistance: 12");
+        assert_eq!(fs::read_to_string(config_dir.join("shared.txt")).unwrap(), "client");
+        assert!(!config_dir.join("server.txt").exists());
+        // user edits to overrides are kept as long as the pack doesn't change the file
+        fs::write(config_dir.join("options.txt"), "renderDistance: 32").unwrap();
+        install(&http_client, &pack, &instance_dir, &cancel, |_, _| {}).await.unwrap();
+        assert_eq!(fs::read_to_string(config_dir.join("options.txt")).unwrap(), "renderDistance: 32");
+        fs::remove_file(config_dir.join("shared.txt")).unwrap();
+        let pack = write_pack(&dir, &index(&[]), &[("overrides/config/options.txt", "renderDistance: 16"), ("client-overrides/config/shared.txt", "client")]);
+        install(&http_client, &pack, &instance_dir, &cancel, |_, _| {}).await.unwrap();
+        assert_eq!(fs::read_to_string(config_dir.join("options.txt")).unwrap(), "renderDistance: 16");
+        assert_eq!(fs::read_to_string(config_dir.join("shared.txt")).unwrap(), "client", "missing overrides are restored");
+        assert!(!instance_dir.join("mods").join("sodium.jar").exists(), "files removed from the pack are deleted");
+    }
+
+    #[tokio::test]
+    async fn install_rejects_local_downloads() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("mrpack-local-download");
+        let secret = dir.join("secret.txt");
+        fs::write(&secret, "secret").unwrap();
+        let pack = write_pack(&dir, &index(&[("config/secret.txt", "secret", &[secret.display().to_string()])]), &[]);
+        let result = install(&test_util::http_client(), &pack, &dir.join("instance"), &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::AllDownloadsFailed(_, download::Error::NotHttps(_)))));
+        assert!(!dir.join("instance").join("config").join("secret.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn install_rejects_unsupported_format() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("mrpack-format");
+        let pack = write_pack(&dir, r#"{"formatVersion": 2, "files": []}"#, &[]);
+        let result = install(&test_util::http_client(), &pack, &dir.join("instance"), &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::FormatVersion(2))));
+    }
+}
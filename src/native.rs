@@ -0,0 +1,733 @@
+//! The built-in launcher, which installs Minecraft and the Fabric loader from their metadata servers and starts Java directly.
+
+use {
+    std::{
+        collections::{
+            BTreeMap,
+            HashMap,
+            HashSet,
+        },
+        io,
+        path::{
+            Path,
+            PathBuf,
+        },
+        sync::atomic::{
+            AtomicBool,
+            Ordering::*,
+        },
+    },
+    futures::stream::{
+        self,
+        StreamExt as _,
+    },
+    serde::{
+        Deserialize,
+        de::DeserializeOwned,
+    },
+    tokio::{
+        process::Command,
+        sync::watch,
+    },
+    wheel::fs,
+    zip::ZipArchive,
+    crate::{
+        auth::Account,
+        config,
+        download::{
+            self,
+            HashFormat,
+            Location,
+        },
+        loader::Loader,
+        quick_play::JoinMethod,
+    },
+};
+
+/// The number of files downloaded at the same time.
+const CONCURRENT_DOWNLOADS: usize = 8;
+/// Where libraries without a repository URL are downloaded from.
+const DEFAULT_MAVEN: &str = "https://libraries.minecraft.net/";
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Config(#[from] config::Error),
+    #[error(transparent)] Download(#[from] download::Error),
+    #[error(transparent)] Io(#[from] io::Error),
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Task(#[from] tokio::task::JoinError),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error(transparent)] Zip(#[from] zip::result::ZipError),
+    #[error("invalid Maven coordinates: {0}")]
+    MavenCoordinates(String),
+    #[error("no Fabric loader available for Minecraft {0}")]
+    NoFabricLoader(String),
+    #[error("the built-in launcher doesn't support {0}, consider configuring a different mod loader in the `modLoaders` config entry")]
+    UnsupportedLoader(Loader),
+    #[error("Minecraft {0} is not in the version manifest")]
+    UnknownVersion(String),
+}
+
+#[derive(Deserialize)]
+struct VersionManifest {
+    latest: LatestVersions,
+    versions: Vec<ManifestVersion>,
+}
+
+#[derive(Deserialize)]
+struct LatestVersions {
+    release: String,
+}
+
+#[derive(Deserialize)]
+struct ManifestVersion {
+    id: String,
+    url: String,
+    sha1: String,
+}
+
+/// A version JSON from Mojang's metadata server, or the relevant parts of a Fabric loader profile, which has the same format.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionJson {
+    id: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    main_class: String,
+    arguments: Option<Arguments>,
+    /// The game arguments of versions older than 1.13, separated by spaces.
+    minecraft_arguments: Option<String>,
+    asset_index: Option<AssetIndexInfo>,
+    downloads: Option<VersionDownloads>,
+    #[serde(default)]
+    libraries: Vec<Library>,
+}
+
+#[derive(Default, Deserialize)]
+struct Arguments {
+    #[serde(default)]
+    game: Vec<Argument>,
+    #[serde(default)]
+    jvm: Vec<Argument>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArgumentValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct Rule {
+    action: RuleAction,
+    os: Option<OsRule>,
+    features: Option<BTreeMap<String, bool>>,
+}
+
+#[derive(PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+#[derive(Deserialize)]
+struct OsRule {
+    name: Option<String>,
+    arch: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+struct AssetIndexInfo {
+    id: String,
+    url: String,
+    sha1: String,
+}
+
+#[derive(Deserialize)]
+struct VersionDownloads {
+    client: Artifact,
+}
+
+#[derive(Deserialize)]
+struct Library {
+    /// Maven coordinates.
+    name: String,
+    downloads: Option<LibraryDownloads>,
+    /// Maps OS names to the classifiers of the library's native code, for versions older than 1.19.
+    natives: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    rules: Vec<Rule>,
+    /// The Maven repository of a library from a Fabric loader profile.
+    url: Option<String>,
+    sha1: Option<String>,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct LibraryDownloads {
+    artifact: Option<Artifact>,
+    #[serde(default)]
+    classifiers: BTreeMap<String, Artifact>,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    path: Option<String>,
+    url: String,
+    sha1: String,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AssetIndex {
+    objects: BTreeMap<String, AssetObject>,
+    /// Versions older than 1.7 need the assets laid out by name rather than hash.
+    #[serde(default, rename = "virtual")]
+    is_virtual: bool,
+}
+
+#[derive(Deserialize)]
+struct AssetObject {
+    hash: String,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct FabricLoaderEntry {
+    loader: FabricLoaderVersion,
+}
+
+#[derive(Deserialize)]
+struct FabricLoaderVersion {
+    version: String,
+    #[serde(default)]
+    stable: bool,
+}
+
+/// The name Mojang's metadata uses for the current OS.
+const OS_NAME: &str = if cfg!(windows) { "windows" } else if cfg!(target_os = "macos") { "osx" } else { "linux" };
+
+/// Evaluates a library's or argument's rules. None of the optional features like demo mode or custom resolutions are enabled.
+fn rules_allow(rules: &[Rule]) -> bool {
+    if rules.is_empty() { return true }
+    let mut allowed = false;
+    for rule in rules {
+        let os_matches = rule.os.as_ref().is_none_or(|os|
+            os.name.as_deref().is_none_or(|name| name == OS_NAME)
+            && os.arch.as_deref().is_none_or(|arch| arch == "x86" && cfg!(target_arch = "x86"))
+        );
+        let features_match = rule.features.as_ref().is_none_or(|features| features.values().all(|&required| !required));
+        if os_matches && features_match {
+            allowed = rule.action == RuleAction::Allow;
+        }
+    }
+    allowed
+}
+
+/// Converts Maven coordinates (`group:artifact:version[:classifier]`) to the path of the jar within a repository.
+fn maven_path(name: &str) -> Result<String, Error> {
+    let mut parts = name.split(':');
+    let (Some(group), Some(artifact), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(Error::MavenCoordinates(name.to_owned()))
+    };
+    let file_name = if let Some(classifier) = parts.next() {
+        format!("{artifact}-{version}-{classifier}.jar")
+    } else {
+        format!("{artifact}-{version}.jar")
+    };
+    Ok(format!("{}/{artifact}/{version}/{file_name}", group.replace('.', "/")))
+}
+
+/// Identifies a library regardless of its version, so the Fabric loader profile can replace libraries of the base game.
+fn library_key(name: &str) -> String {
+    let mut parts = name.split(':');
+    let group = parts.next().unwrap_or_default();
+    let artifact = parts.next().unwrap_or_default();
+    let classifier = parts.nth(1).unwrap_or_default();
+    format!("{group}:{artifact}:{classifier}")
+}
+
+/// A file to download into the cache.
+struct Download {
+    location: Location,
+    path: PathBuf,
+    sha1: Option<String>,
+    /// If a file with this size is already in the cache, it's used without checking its hash, since hashing all assets on every launch would be slow. Files left incomplete by an interrupted download are still replaced.
+    size: Option<u64>,
+}
+
+impl Download {
+    async fn run(self, http_client: &reqwest::Client, cancel: &watch::Receiver<bool>) -> Result<(), Error> {
+        download::check_cancelled(cancel)?;
+        if let Some(size) = self.size && tokio::fs::metadata(&self.path).await.is_ok_and(|metadata| metadata.len() == size) {
+            return Ok(())
+        }
+        if let Some(sha1) = self.sha1 {
+            download::install_verified(http_client, &self.location, &self.path, HashFormat::Sha1, &sha1).await?;
+        } else if !fs::exists(&self.path).await? {
+            // without a hash or size, only writing atomically ensures an existing file is complete
+            download::write_atomic(&self.path, self.location.read(http_client).await?).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the JSON document at `location` and stores it at `path`. If fetching fails, the copy stored by a previous call is used instead, so installed versions can be launched offline.
+async fn fetch_cached<T: DeserializeOwned>(http_client: &reqwest::Client, location: &Location, path: &Path) -> Result<T, Error> {
+    let data = match location.read(http_client).await {
+        Ok(data) => {
+            download::write_atomic(path, &data).await?;
+            data
+        }
+        Err(e) => if fs::exists(path).await? {
+            log::warn!("failed to fetch {location}, using cached copy: {e}");
+            fs::read(path).await?
+        } else {
+            return Err(e.into())
+        },
+    };
+    Ok(serde_json::from_str(&String::from_utf8_lossy(&data))?)
+}
+
+async fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    Ok(serde_json::from_str(&String::from_utf8_lossy(&fs::read(path).await?))?)
+}
+
+/// A game version installed into the cache, ready to be launched.
+pub(crate) struct Installation {
+    version_id: String,
+    version_type: String,
+    main_class: String,
+    arguments: Arguments,
+    /// Game arguments of versions older than 1.13.
+    legacy_game_args: Option<String>,
+    classpath: Vec<PathBuf>,
+    libraries_dir: PathBuf,
+    natives_dir: PathBuf,
+    assets_dir: PathBuf,
+    /// The folder the game reads its assets from, which differs from `assets_dir` for versions older than 1.7.
+    game_assets_dir: PathBuf,
+    asset_index: String,
+}
+
+/// Installs the given game version (or the latest release) with the given mod loader into the cache, reusing files that are already there.
+///
+/// `progress` is called with the number of files downloaded so far and the total number of files. If `cancel` is set, this stops between files with [`download::Error::Cancelled`], letting downloads that have already started finish.
+pub(crate) async fn install(http_client: &reqwest::Client, config: &config::NativeLauncher, game_version: Option<&str>, loader: Loader, loader_version: Option<&str>, cancel: &watch::Receiver<bool>, mut progress: impl FnMut(usize, usize)) -> Result<Installation, Error> {
+    let cache_dir = if let Some(ref cache_dir) = config.cache_dir { cache_dir.clone() } else { config::data_dir()?.join("cache") };
+    let versions_dir = cache_dir.join("versions");
+    let libraries_dir = cache_dir.join("libraries");
+    let assets_dir = cache_dir.join("assets");
+    let manifest = fetch_cached::<VersionManifest>(http_client, &Location::parse(&config.version_manifest)?, &versions_dir.join("version_manifest_v2.json")).await?;
+    let game_version = game_version.unwrap_or(&manifest.latest.release);
+    let manifest_version = manifest.versions.iter().find(|version| version.id == game_version).ok_or_else(|| Error::UnknownVersion(game_version.to_owned()))?;
+    let version_dir = versions_dir.join(&manifest_version.id);
+    let version_json_path = version_dir.join(format!("{}.json", manifest_version.id));
+    download::install_verified(http_client, &Location::parse(&manifest_version.url)?, &version_json_path, HashFormat::Sha1, &manifest_version.sha1).await?;
+    let version = read_json::<VersionJson>(&version_json_path).await?;
+    let loader_profile = match loader {
+        Loader::Vanilla => None,
+        Loader::Fabric => {
+            let fabric_meta = Location::parse(&config.fabric_meta)?;
+            let loader_version = if let Some(loader_version) = loader_version {
+                loader_version.to_owned()
+            } else {
+                let loaders = fetch_cached::<Vec<FabricLoaderEntry>>(http_client, &fabric_meta.join(&format!("v2/versions/loader/{game_version}"))?, &version_dir.join("fabric-loaders.json")).await?;
+                let entry = loaders.iter().find(|entry| entry.loader.stable).or_else(|| loaders.first()).ok_or_else(|| Error::NoFabricLoader(game_version.to_owned()))?;
+                entry.loader.version.clone()
+            };
+            let profile_location = fabric_meta.join(&format!("v2/versions/loader/{game_version}/{loader_version}/profile/json"))?;
+            Some(fetch_cached::<VersionJson>(http_client, &profile_location, &version_dir.join(format!("fabric-{loader_version}.json"))).await?)
+        }
+        Loader::Quilt | Loader::NeoForge => return Err(Error::UnsupportedLoader(loader)),
+    };
+    let asset_index_info = version.asset_index.clone().ok_or_else(|| Error::UnknownVersion(game_version.to_owned()))?;
+    let asset_index_path = assets_dir.join("indexes").join(format!("{}.json", asset_index_info.id));
+    download::install_verified(http_client, &Location::parse(&asset_index_info.url)?, &asset_index_path, HashFormat::Sha1, &asset_index_info.sha1).await?;
+    let asset_index = read_json::<AssetIndex>(&asset_index_path).await?;
+    let mut downloads = Vec::default();
+    let mut classpath = Vec::default();
+    let mut natives = Vec::default();
+    let mut seen_libraries = HashSet::new();
+    // the loader's libraries come first so they replace different versions of the same libraries from the base game
+    for library in loader_profile.iter().flat_map(|profile| &profile.libraries).chain(&version.libraries) {
+        if !rules_allow(&library.rules) { continue }
+        if !seen_libraries.insert(library_key(&library.name)) { continue }
+        if let Some(ref classifiers) = library.natives {
+            if let Some(classifier) = classifiers.get(OS_NAME) {
+                let classifier = classifier.replace("${arch}", if cfg!(target_pointer_width = "64") { "64" } else { "32" });
+                if let Some(artifact) = library.downloads.as_ref().and_then(|downloads| downloads.classifiers.get(&classifier)) {
+                    let path = libraries_dir.join(download::relative_path(&match artifact.path {
+                        Some(ref path) => path.clone(),
+                        None => maven_path(&format!("{}:{classifier}", library.name))?,
+                    })?);
+                    downloads.push(Download { location: Location::parse(&artifact.url)?, path: path.clone(), sha1: Some(artifact.sha1.clone()), size: artifact.size });
+                    natives.push(path);
+                }
+            }
+            // libraries with native code for other platforms have no regular artifact
+            if library.downloads.as_ref().is_none_or(|downloads| downloads.artifact.is_none()) { continue }
+        }
+        let download = if let Some(artifact) = library.downloads.as_ref().and_then(|downloads| downloads.artifact.as_ref()) {
+            let relative = match artifact.path {
+                Some(ref path) => path.clone(),
+                None => maven_path(&library.name)?,
+            };
+            Download { location: Location::parse(&artifact.url)?, path: libraries_dir.join(download::relative_path(&relative)?), sha1: Some(artifact.sha1.clone()), size: artifact.size }
+        } else {
+            let relative = maven_path(&library.name)?;
+            Download {
+                location: Location::parse(library.url.as_deref().unwrap_or(DEFAULT_MAVEN))?.join(&relative)?,
+                path: libraries_dir.join(download::relative_path(&relative)?),
+                sha1: library.sha1.clone(),
+                size: library.size,
+            }
+        };
+        classpath.push(download.path.clone());
+        downloads.push(download);
+    }
+    if let Some(ref version_downloads) = version.downloads {
+        let client_path = version_dir.join(format!("{}.jar", version.id));
+        downloads.push(Download { location: Location::parse(&version_downloads.client.url)?, path: client_path.clone(), sha1: Some(version_downloads.client.sha1.clone()), size: version_downloads.client.size });
+        classpath.push(client_path);
+    }
+    let resources = Location::parse(&config.resources)?;
+    let mut seen_objects = HashSet::new();
+    for object in asset_index.objects.values() {
+        if !seen_objects.insert(&object.hash) { continue }
+        let relative = format!("{}/{}", &object.hash[..2.min(object.hash.len())], object.hash);
+        downloads.push(Download {
+            location: resources.join(&relative)?,
+            path: assets_dir.join("objects").join(download::relative_path(&relative)?),
+            sha1: Some(object.hash.clone()),
+            size: object.size,
+        });
+    }
+    let total = downloads.len();
+    progress(0, total);
+    let mut done = 0;
+    let failed = AtomicBool::default();
+    let mut results = stream::iter(downloads).map(|download| {
+        let failed = &failed;
+        async move {
+            // after an error, downloads that haven't started yet are skipped
+            if failed.load(Relaxed) { return None }
+            Some(download.run(http_client, cancel).await)
+        }
+    }).buffer_unordered(CONCURRENT_DOWNLOADS);
+    let mut first_error = None;
+    // returning early would drop downloads that are in progress, so they're allowed to finish first
+    while let Some(result) = results.next().await {
+        match result {
+            Some(Ok(())) => {
+                done += 1;
+                progress(done, total);
+            }
+            Some(Err(e)) => {
+                failed.store(true, Relaxed);
+                first_error.get_or_insert(e);
+            }
+            None => {}
+        }
+    }
+    if let Some(e) = first_error {
+        return Err(e)
+    }
+    let game_assets_dir = if asset_index.is_virtual {
+        let virtual_dir = assets_dir.join("virtual").join(&asset_index_info.id);
+        for (name, object) in &asset_index.objects {
+            download::check_cancelled(cancel)?;
+            let path = virtual_dir.join(download::relative_path(name)?);
+            if fs::exists(&path).await? { continue }
+            let relative = format!("{}/{}", &object.hash[..2.min(object.hash.len())], object.hash);
+            download::write_atomic(&path, fs::read(assets_dir.join("objects").join(download::relative_path(&relative)?)).await?).await?;
+        }
+        virtual_dir
+    } else {
+        assets_dir.clone()
+    };
+    let natives_dir = version_dir.join("natives");
+    if !natives.is_empty() {
+        let natives_dir = natives_dir.clone();
+        tokio::task::spawn_blocking(move || extract_natives(&natives, &natives_dir)).await??;
+    }
+    let (main_class, arguments, legacy_game_args) = if let Some(profile) = loader_profile {
+        let mut arguments = version.arguments.unwrap_or_default();
+        if let Some(profile_arguments) = profile.arguments {
+            arguments.jvm.extend(profile_arguments.jvm);
+            arguments.game.extend(profile_arguments.game);
+        }
+        (profile.main_class, arguments, version.minecraft_arguments)
+    } else {
+        (version.main_class, version.arguments.unwrap_or_default(), version.minecraft_arguments)
+    };
+    Ok(Installation {
+        version_id: version.id,
+        version_type: version.kind.unwrap_or_else(|| format!("release")),
+        asset_index: asset_index_info.id,
+        main_class, arguments, legacy_game_args, classpath, libraries_dir, natives_dir, assets_dir, game_assets_dir,
+    })
+}
+
+/// Extracts the native code of libraries for versions older than 1.19, which don't extract it themselves.
+fn extract_natives(jars: &[PathBuf], natives_dir: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(natives_dir)?;
+    for jar in jars {
+        let mut zip = ZipArchive::new(std::fs::File::open(jar)?)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() || file.name().starts_with("META-INF/") { continue }
+            let Some(relative) = file.enclosed_name() else { continue };
+            let path = natives_dir.join(relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut std::fs::File::create(path)?)?;
+        }
+    }
+    Ok(())
+}
+
+impl Installation {
+    /// The command that starts the game in `game_dir`, joining `server_address` if specified.
+    ///
+    /// `java_path` defaults to the `java` executable in the `PATH`, and `jvm_args` are passed before the game's own JVM arguments.
+    pub(crate) fn command(&self, java_path: Option<&Path>, jvm_args: &[String], account: &Account, game_dir: &Path, server_address: Option<&str>) -> Command {
+        let classpath_separator = if cfg!(windows) { ";" } else { ":" };
+        let classpath = self.classpath.iter().map(|path| path.to_string_lossy()).collect::<Vec<_>>().join(classpath_separator);
+        let variables = HashMap::from([
+            ("auth_player_name", account.name.clone()),
+            ("auth_uuid", account.uuid.simple().to_string()),
+            ("auth_access_token", account.access_token.clone()),
+            ("auth_session", account.access_token.clone()),
+            ("auth_xuid", account.xuid.clone().unwrap_or_default()),
+            ("clientid", String::default()),
+            ("user_type", format!("msa")),
+            ("user_properties", format!("{{}}")),
+            ("version_name", self.version_id.clone()),
+            ("version_type", self.version_type.clone()),
+            ("game_directory", game_dir.to_string_lossy().into_owned()),
+            ("assets_root", self.assets_dir.to_string_lossy().into_owned()),
+            ("game_assets", self.game_assets_dir.to_string_lossy().into_owned()),
+            ("assets_index_name", self.asset_index.clone()),
+            ("natives_directory", self.natives_dir.to_string_lossy().into_owned()),
+            ("library_directory", self.libraries_dir.to_string_lossy().into_owned()),
+            ("classpath_separator", classpath_separator.to_owned()),
+            ("classpath", classpath),
+            ("launcher_name", env!("CARGO_PKG_NAME").to_owned()),
+            ("launcher_version", env!("CARGO_PKG_VERSION").to_owned()),
+        ]);
+        let mut cmd = Command::new(java_path.unwrap_or(Path::new(if cfg!(windows) { "javaw" } else { "java" })));
+        cmd.args(jvm_args);
+        if self.legacy_game_args.is_some() {
+            // versions older than 1.13 leave these to the launcher
+            cmd.arg(substitute("-Djava.library.path=${natives_directory}", &variables));
+            cmd.arg("-cp");
+            cmd.arg(&variables["classpath"]);
+        }
+        cmd.args(expand(&self.arguments.jvm, &variables));
+        cmd.arg(&self.main_class);
+        if let Some(ref legacy_game_args) = self.legacy_game_args {
+            cmd.args(legacy_game_args.split_whitespace().map(|arg| substitute(arg, &variables)));
+        }
+        cmd.args(expand(&self.arguments.game, &variables));
+        if let Some(server_address) = server_address {
+            let join_method = JoinMethod::for_version(Some(self.version_id.as_str()));
+            log::info!("joining {server_address} using {join_method}");
+            cmd.args(join_method.game_args(server_address));
+        }
+        cmd.current_dir(game_dir);
+        cmd
+    }
+}
+
+fn expand(arguments: &[Argument], variables: &HashMap<&str, String>) -> Vec<String> {
+    let mut args = Vec::default();
+    for argument in arguments {
+        match argument {
+            Argument::Plain(arg) => args.push(substitute(arg, variables)),
+            Argument::Conditional { rules, value } => if rules_allow(rules) {
+                match value {
+                    ArgumentValue::Single(arg) => args.push(substitute(arg, variables)),
+                    ArgumentValue::Multiple(values) => args.extend(values.iter().map(|arg| substitute(arg, variables))),
+                }
+            },
+        }
+    }
+    args
+}
+
+/// Replaces `${name}` placeholders with the values of the variables. Unknown placeholders are left as is.
+fn substitute(arg: &str, variables: &HashMap<&str, String>) -> String {
+    let mut result = String::default();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else { break };
+        let name = &rest[start + 2..start + len];
+        result.push_str(&rest[..start]);
+        if let Some(value) = variables.get(name) {
+            result.push_str(value);
+        } else {
+            result.push_str(&rest[start..=start + len]);
+        }
+        rest = &rest[start + len + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::fs,
+        crate::test_util,
+        super::*,
+    };
+
+    /// Writes a file of the stand-in metadata servers and returns its SHA-1 hash.
+    fn write_file(path: &Path, contents: &str) -> String {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        HashFormat::Sha1.hash(contents.as_bytes())
+    }
+
+    /// A path as a JSON string.
+    fn json_path(path: &Path) -> String {
+        format!("{:?}", path.display().to_string())
+    }
+
+    /// Lays out stand-ins for Mojang's and Fabric's metadata servers with a version `1.21.1` and Fabric loader `0.16.5` in `dir`.
+    fn write_servers(dir: &Path) -> config::NativeLauncher {
+        let mojang_dir = dir.join("mojang");
+        let resources_dir = dir.join("resources");
+        let fabric_dir = dir.join("fabric");
+        let asset = "{\"language.name\": \"English\"}";
+        let asset_hash = HashFormat::Sha1.hash(asset.as_bytes());
+        write_file(&resources_dir.join(&asset_hash[..2]).join(&asset_hash), asset);
+        let asset_index_hash = write_file(&mojang_dir.join("assets.json"), &format!(r#"{{"objects": {{"minecraft/lang/en_us.json": {{"hash": "{asset_hash}", "size": {}}}}}}}"#, asset.len()));
+        let client_hash = write_file(&mojang_dir.join("client.jar"), "client");
+        let library_hash = write_file(&mojang_dir.join("libraries").join("library.jar"), "library");
+        let version_hash = write_file(&mojang_dir.join("1.21.1.json"), &format!(r#"{{
+            "id": "1.21.1",
+            "type": "release",
+            "mainClass": "net.minecraft.client.main.Main",
+            "arguments": {{"game": ["--username", "${{auth_player_name}}"], "jvm": ["-cp", "${{classpath}}"]}},
+            "assetIndex": {{"id": "17", "url": {}, "sha1": "{asset_index_hash}"}},
+            "downloads": {{"client": {{"url": {}, "sha1": "{client_hash}", "size": 6}}}},
+            "libraries": [
+                {{"name": "com.example:library:1.0", "downloads": {{"artifact": {{"path": "com/example/library/1.0/library-1.0.jar", "url": {}, "sha1": "{library_hash}", "size": 7}}}}}},
+                {{"name": "com.example:other-os:1.0", "downloads": {{"artifact": {{"url": {}, "sha1": "{library_hash}"}}}}, "rules": [{{"action": "allow", "os": {{"name": "other-os"}}}}]}}
+            ]
+        }}"#, json_path(&mojang_dir.join("assets.json")), json_path(&mojang_dir.join("client.jar")), json_path(&mojang_dir.join("libraries").join("library.jar")), json_path(&mojang_dir.join("libraries").join("library.jar"))));
+        write_file(&mojang_dir.join("version_manifest_v2.json"), &format!(r#"{{
+            "latest": {{"release": "1.21.1", "snapshot": "1.21.1"}},
+            "versions": [{{"id": "1.21.1", "type": "release", "url": {}, "sha1": "{version_hash}"}}]
+        }}"#, json_path(&mojang_dir.join("1.21.1.json"))));
+        let maven_dir = dir.join("maven");
+        let loader_hash = write_file(&maven_dir.join("net/fabricmc/fabric-loader/0.16.5/fabric-loader-0.16.5.jar"), "loader");
+        // older Fabric profiles don't list hashes
+        write_file(&maven_dir.join("net/fabricmc/intermediary/1.21.1/intermediary-1.21.1.jar"), "intermediary");
+        write_file(&fabric_dir.join("v2/versions/loader/1.21.1/0.16.5/profile/json"), &format!(r#"{{
+            "id": "fabric-loader-0.16.5-1.21.1",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "arguments": {{"game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]}},
+            "libraries": [
+                {{"name": "net.fabricmc:fabric-loader:0.16.5", "url": {maven}, "sha1": "{loader_hash}", "size": 6}},
+                {{"name": "net.fabricmc:intermediary:1.21.1", "url": {maven}}}
+            ]
+        }}"#, maven = json_path(&maven_dir)));
+        config::NativeLauncher {
+            game_dir: None,
+            cache_dir: Some(dir.join("cache")),
+            version_manifest: mojang_dir.join("version_manifest_v2.json").display().to_string(),
+            resources: resources_dir.display().to_string(),
+            fabric_meta: fabric_dir.display().to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn install_fabric_offline() {
+        let dir = test_util::temp_dir("native-install");
+        let mut config = write_servers(&dir);
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let http_client = test_util::http_client();
+        let mut last_progress = None;
+        let installation = install(&http_client, &config, None, Loader::Fabric, Some("0.16.5"), &cancel, |done, total| last_progress = Some((done, total))).await.unwrap();
+        // client, one library for this OS, two Fabric libraries, one asset
+        assert_eq!(last_progress, Some((5, 5)));
+        assert_eq!(installation.version_id, "1.21.1");
+        assert_eq!(installation.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        let cache_dir = dir.join("cache");
+        let libraries_dir = cache_dir.join("libraries");
+        assert_eq!(installation.classpath, [
+            libraries_dir.join("net/fabricmc/fabric-loader/0.16.5/fabric-loader-0.16.5.jar"),
+            libraries_dir.join("net/fabricmc/intermediary/1.21.1/intermediary-1.21.1.jar"),
+            libraries_dir.join("com/example/library/1.0/library-1.0.jar"),
+            cache_dir.join("versions").join("1.21.1").join("1.21.1.jar"),
+        ]);
+        assert_eq!(fs::read_to_string(&installation.classpath[3]).unwrap(), "client");
+        // the cached metadata is used when the servers are unreachable, and incomplete files are downloaded again
+        fs::write(&installation.classpath[3], "cli").unwrap();
+        fs::remove_dir_all(dir.join("mojang")).unwrap();
+        write_file(&dir.join("mojang").join("client.jar"), "client");
+        config.fabric_meta = dir.join("missing").display().to_string();
+        let installation = install(&http_client, &config, Some("1.21.1"), Loader::Fabric, Some("0.16.5"), &cancel, |_, _| {}).await.unwrap();
+        assert_eq!(fs::read_to_string(&installation.classpath[3]).unwrap(), "client");
+        // 1.21.1 supports Quick Play
+        let account = Account { name: format!("Player"), uuid: uuid::Uuid::nil(), access_token: format!("token"), xuid: None };
+        let cmd = installation.command(None, &[], &account, &dir.join("game"), Some("wurstmineberg.de"));
+        let args = cmd.as_std().get_args().map(|arg| arg.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        assert!(args.windows(2).any(|pair| pair == ["--quickPlayMultiplayer", "wurstmineberg.de"]), "missing Quick Play arguments in {args:?}");
+        assert!(!args.iter().any(|arg| arg == "--server"));
+    }
+
+    #[tokio::test]
+    async fn install_rejects_hash_mismatch() {
+        let dir = test_util::temp_dir("native-hash-mismatch");
+        let config = write_servers(&dir);
+        fs::write(dir.join("mojang").join("client.jar"), "modded").unwrap();
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let result = install(&test_util::http_client(), &config, Some("1.21.1"), Loader::Vanilla, None, &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::Download(download::Error::HashMismatch { .. }))));
+    }
+
+    #[tokio::test]
+    async fn unknown_version() {
+        let dir = test_util::temp_dir("native-unknown-version");
+        let config = write_servers(&dir);
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let result = install(&test_util::http_client(), &config, Some("1.7.10"), Loader::Vanilla, None, &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::UnknownVersion(version)) if version == "1.7.10"));
+    }
+
+    #[tokio::test]
+    async fn unverified_download_is_atomic() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("native-unverified");
+        let source = dir.join("server").join("library.jar");
+        write_file(&source, "library");
+        let path = dir.join("cache").join("library.jar");
+        // a file left behind by an interrupted download isn't mistaken for a complete one
+        write_file(&dir.join("cache").join(".library.jar.part"), "lib");
+        let download = || Download { location: Location::Path(source.clone()), path: path.clone(), sha1: None, size: None };
+        download().run(&test_util::http_client(), &cancel).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "library");
+        assert!(!dir.join("cache").join(".library.jar.part").exists());
+        // complete files without a hash or size are reused
+        fs::remove_file(&source).unwrap();
+        download().run(&test_util::http_client(), &cancel).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "library");
+    }
+}
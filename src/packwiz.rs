@@ -0,0 +1,208 @@
+//! Native support for installing <https://packwiz.infra.link/> modpacks, without requiring packwiz-installer or Java.
+
+use {
+    std::{
+        collections::{
+            BTreeMap,
+            BTreeSet,
+        },
+        path::Path,
+    },
+    serde::Deserialize,
+    tokio::sync::watch,
+    crate::download::{
+        self,
+        HashFormat,
+        Location,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Download(#[from] download::Error),
+    #[error(transparent)] Toml(#[from] toml::de::Error),
+    #[error("packwiz file {0} has no download URL (CurseForge mods are not supported)")]
+    MissingUrl(String),
+}
+
+#[derive(Deserialize)]
+struct PackFile {
+    index: IndexRef,
+    #[serde(default)]
+    versions: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexRef {
+    file: String,
+    hash_format: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Index {
+    hash_format: String,
+    #[serde(default)]
+    files: Vec<IndexEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexEntry {
+    file: String,
+    hash: String,
+    /// Overrides the index's `hash-format` for this file.
+    hash_format: Option<String>,
+    #[serde(default)]
+    metafile: bool,
+}
+
+#[derive(Deserialize)]
+struct MetaFile {
+    filename: String,
+    #[serde(default)]
+    side: Side,
+    download: MetaDownload,
+}
+
+#[derive(Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Side {
+    #[default]
+    Both,
+    Client,
+    Server,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct MetaDownload {
+    url: Option<String>,
+    hash_format: String,
+    hash: String,
+}
+
+/// Installs or updates the pack at `pack` into `instance_dir` and removes files that were previously installed from it but are no longer part of it.
+///
+/// `progress` is called with the number of files processed so far and the total number of files. If `cancel` is set, this stops between files with [`download::Error::Cancelled`].
+///
+/// Returns the pack's `[versions]` table, e.g. `minecraft` and `fabric`.
+pub(crate) async fn sync(http_client: &reqwest::Client, pack: &str, instance_dir: &Path, cancel: &watch::Receiver<bool>, mut progress: impl FnMut(usize, usize)) -> Result<BTreeMap<String, String>, Error> {
+    let pack_location = Location::parse(pack)?;
+    let pack_file = toml::from_slice::<PackFile>(&pack_location.read(http_client).await?)?;
+    let index_location = pack_location.sibling(&pack_file.index.file)?;
+    let index = toml::from_slice::<Index>(&download::fetch_verified(http_client, &index_location, HashFormat::parse(&pack_file.index.hash_format)?, &pack_file.index.hash).await?)?;
+    let mut installed = BTreeSet::default();
+    let total = index.files.len();
+    for (done, entry) in index.files.into_iter().enumerate() {
+        download::check_cancelled(cancel)?;
+        progress(done, total);
+        let entry_location = index_location.sibling(&entry.file)?;
+        let hash_format = HashFormat::parse(entry.hash_format.as_deref().unwrap_or(&index.hash_format))?;
+        let (target, location, hash_format, hash) = if entry.metafile {
+            let meta = toml::from_slice::<MetaFile>(&download::fetch_verified(http_client, &entry_location, hash_format, &entry.hash).await?)?;
+            if meta.side == Side::Server { continue }
+            let target = match entry.file.rsplit_once('/') {
+                Some((dir, _)) => format!("{dir}/{}", meta.filename),
+                None => meta.filename,
+            };
+            let url = meta.download.url.ok_or_else(|| Error::MissingUrl(entry.file.clone()))?;
+            (target, Location::parse(&url)?, HashFormat::parse(&meta.download.hash_format)?, meta.download.hash)
+        } else {
+            (entry.file, entry_location, hash_format, entry.hash)
+        };
+        download::install_verified(http_client, &location, &instance_dir.join(download::relative_path(&target)?), hash_format, &hash).await?;
+        installed.insert(target);
+    }
+    progress(total, total);
+    download::replace_installed(instance_dir, ".wurstmineberg-packwiz.json", installed).await?;
+    Ok(pack_file.versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::fs,
+        crate::test_util,
+        super::*,
+    };
+
+    fn sha256(data: &str) -> String {
+        HashFormat::Sha256.hash(data.as_bytes())
+    }
+
+    /// Writes a pack with the given index entries to `dir` and returns the path to its `pack.toml`.
+    fn write_pack(dir: &Path, entries: &[(&str, &str, bool)]) -> String {
+        let mut index = format!("hash-format = \"sha256\"\n");
+        for &(file, contents, metafile) in entries {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            index.push_str(&format!("\n[[files]]\nfile = \"{file}\"\nhash = \"{}\"\nmetafile = {metafile}\n", sha256(contents)));
+        }
+        fs::write(dir.join("index.toml"), &index).unwrap();
+        fs::write(dir.join("pack.toml"), format!("name = \"Test\"\n\n[index]\nfile = \"index.toml\"\nhash-format = \"sha256\"\nhash = \"{}\"\n\n[versions]\nminecraft = \"1.21.1\"\nfabric = \"0.16.5\"\n", sha256(&index))).unwrap();
+        dir.join("pack.toml").display().to_string()
+    }
+
+    fn meta_file(filename: &str, url: &Path, contents: &str, side: &str) -> String {
+        format!("name = \"{filename}\"\nfilename = \"{filename}\"\nside = \"{side}\"\n\n[download]\nurl = '{}'\nhash-format = \"sha256\"\nhash = \"{}\"\n", url.display(), sha256(contents))
+    }
+
+    #[tokio::test]
+    async fn sync_local_pack() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("packwiz-sync");
+        let (pack_dir, instance_dir, downloads_dir) = (dir.join("pack"), dir.join("instance"), dir.join("downloads"));
+        fs::create_dir_all(&downloads_dir).unwrap();
+        fs::write(downloads_dir.join("client.jar"), "client mod").unwrap();
+        fs::write(downloads_dir.join("server.jar"), "server mod").unwrap();
+        let client_meta = meta_file("client.jar", &downloads_dir.join("client.jar"), "client mod", "client");
+        let server_meta = meta_file("server.jar", &downloads_dir.join("server.jar"), "server mod", "server");
+        let pack = write_pack(&pack_dir, &[
+            ("config/options.txt", "renderDistance: 12", false),
+            ("mods/client.pw.toml", &client_meta, true),
+            ("mods/server.pw.toml", &server_meta, true),
+        ]);
+        let http_client = test_util::http_client();
+        let mut last_progress = None;
+        let versions = sync(&http_client, &pack, &instance_dir, &cancel, |done, total| last_progress = Some((done, total))).await.unwrap();
+        assert_eq!(versions.get("minecraft").map(String::as_str), Some("1.21.1"));
+        assert_eq!(versions.get("fabric").map(String::as_str), Some("0.16.5"));
+        assert_eq!(last_progress, Some((3, 3)));
+        assert_eq!(fs::read_to_string(instance_dir.join("config").join("options.txt")).unwrap(), "renderDistance: 12");
+        assert_eq!(fs::read_to_string(instance_dir.join("mods").join("client.jar")).unwrap(), "client mod");
+        assert!(!instance_dir.join("mods").join("server.jar").exists(), "server-only mods shouldn't be installed");
+        // files dropped from the pack are removed, files that were never part of it are kept
+        fs::write(instance_dir.join("mods").join("user.jar"), "user mod").unwrap();
+        let pack = write_pack(&pack_dir, &[("config/options.txt", "renderDistance: 12", false)]);
+        sync(&http_client, &pack, &instance_dir, &cancel, |_, _| {}).await.unwrap();
+        assert!(!instance_dir.join("mods").join("client.jar").exists());
+        assert!(instance_dir.join("mods").join("user.jar").exists());
+    }
+
+    #[tokio::test]
+    async fn sync_rejects_hash_mismatch() {
+        let (_cancel_tx, cancel) = watch::channel(false);
+        let dir = test_util::temp_dir("packwiz-hash-mismatch");
+        let (pack_dir, instance_dir) = (dir.join("pack"), dir.join("instance"));
+        let pack = write_pack(&pack_dir, &[("config/options.txt", "renderDistance: 12", false)]);
+        fs::write(pack_dir.join("config").join("options.txt"), "renderDistance: 32").unwrap();
+        let result = sync(&test_util::http_client(), &pack, &instance_dir, &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::Download(download::Error::HashMismatch { .. }))));
+        assert!(!instance_dir.join("config").join("options.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn sync_cancelled() {
+        let (_cancel_tx, cancel) = watch::channel(true);
+        let dir = test_util::temp_dir("packwiz-cancelled");
+        let (pack_dir, instance_dir) = (dir.join("pack"), dir.join("instance"));
+        let pack = write_pack(&pack_dir, &[("config/options.txt", "renderDistance: 12", false)]);
+        let result = sync(&test_util::http_client(), &pack, &instance_dir, &cancel, |_, _| {}).await;
+        assert!(matches!(result, Err(Error::Download(download::Error::Cancelled))));
+        assert!(!instance_dir.join("config").join("options.txt").exists());
+    }
+}
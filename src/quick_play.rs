@@ -0,0 +1,126 @@
+//! Deciding how to make Minecraft join a server on launch, which depends on the game version.
+
+use {
+    std::fmt,
+    crate::version::Version,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JoinMethod {
+    /// The `--quickPlayMultiplayer` game argument.
+    QuickPlay,
+    /// The `--server` and `--port` game arguments, which were replaced by Quick Play.
+    Legacy,
+}
+
+impl JoinMethod {
+    /// Unknown and unparseable versions are assumed to be recent.
+    pub(crate) fn for_version(version: Option<&str>) -> Self {
+        match version.and_then(Version::parse) {
+            // Quick Play was added in snapshot 23w14a, which is part of the 1.20 development cycle
+            Some(version) if !version.is_at_least((1, 20, 0), (23, 14)) => Self::Legacy,
+            _ => Self::QuickPlay,
+        }
+    }
+
+    /// The game arguments that make Minecraft join the server at `address`, which may include a port.
+    pub(crate) fn game_args(&self, address: &str) -> Vec<String> {
+        match self {
+            Self::QuickPlay => vec![format!("--quickPlayMultiplayer"), address.to_owned()],
+            Self::Legacy => {
+                let (host, port) = address.rsplit_once(':').filter(|(_, port)| port.parse::<u16>().is_ok()).unwrap_or((address, "25565"));
+                vec![format!("--server"), host.to_owned(), format!("--port"), port.to_owned()]
+            }
+        }
+    }
+
+    /// Options for the `start` subcommand of portablemc version 5 that pass the [game arguments](Self::game_args) for joining the server at `address`, rather than leaving the choice of arguments to portablemc.
+    pub(crate) fn portablemc_args(&self, address: &str) -> Vec<String> {
+        self.game_args(address).into_iter().map(|arg| format!("--game-arg={arg}")).collect()
+    }
+
+    /// Options for `prismlauncher` that start `instance` and join the server at `address`.
+    ///
+    /// Prism Launcher's `--server` option is only used for the legacy method. For Quick Play, the server is set in the instance settings instead, see [`prism_settings`].
+    pub(crate) fn prism_args(&self, instance: &str, address: &str) -> Vec<String> {
+        match self {
+            Self::QuickPlay => vec![format!("--launch"), instance.to_owned()],
+            Self::Legacy => vec![format!("--launch"), instance.to_owned(), format!("--server"), address.to_owned()],
+        }
+    }
+}
+
+/// The settings of a Prism Launcher instance that make it join a server using Quick Play, given the join method and server address if a server should be joined.
+///
+/// These are written on every launch via Prism Launcher so a server set for a previous launch isn't joined when launching into the main menu.
+pub(crate) fn prism_settings(join: Option<(JoinMethod, &str)>) -> Vec<(&'static str, String)> {
+    match join {
+        Some((JoinMethod::QuickPlay, address)) => vec![("JoinServerOnLaunch", format!("true")), ("JoinServerOnLaunchAddress", address.to_owned())],
+        Some((JoinMethod::Legacy, _)) | None => vec![("JoinServerOnLaunch", format!("false"))],
+    }
+}
+
+impl fmt::Display for JoinMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::QuickPlay => write!(f, "Quick Play"),
+            Self::Legacy => write!(f, "legacy server arguments"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_before_quick_play() {
+        assert_eq!(JoinMethod::for_version(Some("1.19.4")), JoinMethod::Legacy);
+        assert_eq!(JoinMethod::for_version(Some("1.8.9")), JoinMethod::Legacy);
+        assert_eq!(JoinMethod::for_version(Some("23w13a")), JoinMethod::Legacy);
+    }
+
+    #[test]
+    fn quick_play_since_23w14a() {
+        assert_eq!(JoinMethod::for_version(Some("23w14a")), JoinMethod::QuickPlay);
+        assert_eq!(JoinMethod::for_version(Some("1.20")), JoinMethod::QuickPlay);
+        assert_eq!(JoinMethod::for_version(Some("1.21.4")), JoinMethod::QuickPlay);
+    }
+
+    #[test]
+    fn unknown_versions_are_recent() {
+        assert_eq!(JoinMethod::for_version(None), JoinMethod::QuickPlay);
+        assert_eq!(JoinMethod::for_version(Some("not a version")), JoinMethod::QuickPlay);
+    }
+
+    #[test]
+    fn legacy_args() {
+        assert_eq!(JoinMethod::Legacy.game_args("wurstmineberg.de"), ["--server", "wurstmineberg.de", "--port", "25565"]);
+        assert_eq!(JoinMethod::Legacy.game_args("wurstmineberg.de:25566"), ["--server", "wurstmineberg.de", "--port", "25566"]);
+    }
+
+    #[test]
+    fn portablemc_args() {
+        assert_eq!(JoinMethod::QuickPlay.portablemc_args("wurstmineberg.de"), ["--game-arg=--quickPlayMultiplayer", "--game-arg=wurstmineberg.de"]);
+        assert_eq!(JoinMethod::Legacy.portablemc_args("wurstmineberg.de"), ["--game-arg=--server", "--game-arg=wurstmineberg.de", "--game-arg=--port", "--game-arg=25565"]);
+    }
+
+    #[test]
+    fn prism_args() {
+        assert_eq!(JoinMethod::QuickPlay.prism_args("Wurstmineberg", "wurstmineberg.de"), ["--launch", "Wurstmineberg"]);
+        assert_eq!(JoinMethod::Legacy.prism_args("Wurstmineberg", "wurstmineberg.de"), ["--launch", "Wurstmineberg", "--server", "wurstmineberg.de"]);
+    }
+
+    #[test]
+    fn prism_settings() {
+        assert_eq!(super::prism_settings(Some((JoinMethod::QuickPlay, "wurstmineberg.de"))), [("JoinServerOnLaunch", format!("true")), ("JoinServerOnLaunchAddress", format!("wurstmineberg.de"))]);
+        assert_eq!(super::prism_settings(Some((JoinMethod::Legacy, "wurstmineberg.de"))), [("JoinServerOnLaunch", format!("false"))]);
+        assert_eq!(super::prism_settings(None), [("JoinServerOnLaunch", format!("false"))]);
+    }
+
+    #[test]
+    fn quick_play_args() {
+        assert_eq!(JoinMethod::QuickPlay.game_args("wurstmineberg.de"), ["--quickPlayMultiplayer", "wurstmineberg.de"]);
+        assert_eq!(JoinMethod::QuickPlay.game_args("wurstmineberg.de:25566"), ["--quickPlayMultiplayer", "wurstmineberg.de:25566"]);
+    }
+}
@@ -0,0 +1,28 @@
+//! Helpers shared by the unit tests.
+
+use std::path::PathBuf;
+
+/// An empty folder for the test with the given name, unique to this test run.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wurstmineberg-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// An HTTP client like the app's, except that it also allows plain HTTP so it can talk to local stand-in servers.
+pub(crate) fn http_client() -> reqwest::Client {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    reqwest::Client::builder()
+        .tls_backend_rustls()
+        .build()
+        .unwrap()
+}
+
+/// Serves the router on a free local port in the background and returns its base URL, without a trailing slash.
+pub(crate) async fn serve(router: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
@@ -0,0 +1,114 @@
+//! The system tray icon. It's implemented using native-windows-gui on Windows and as a StatusNotifierItem via D-Bus on other platforms, both showing the platform-independent [`TrayModel`].
+
+use {
+    std::sync::Arc,
+    log_lock::Mutex,
+    smart_default::SmartDefault,
+    tokio::sync::{
+        Notify,
+        broadcast,
+    },
+    wheel::traits::SendResultExt as _,
+    crate::{
+        State,
+        config::{
+            self,
+            Config,
+        },
+        gui,
+        logging,
+    },
+};
+pub(crate) use model::{
+    Action,
+    MenuItem,
+    TrayModel,
+};
+#[cfg(windows)] pub(crate) use nwg::run;
+#[cfg(not(windows))] pub(crate) use sni::run;
+
+mod model;
+#[cfg(windows)] mod nwg;
+#[cfg(not(windows))] mod sni;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[cfg(not(windows))] #[error(transparent)] Image(#[from] image::ImageError),
+    #[cfg(not(windows))] #[error(transparent)] Ksni(#[from] ksni::Error),
+    #[cfg(windows)] #[error(transparent)] Nwg(#[from] native_windows_gui::NwgError),
+}
+
+/// The server state as shown by the tray icon: `None` while loading.
+pub(crate) type SharedState = Option<Result<State, Arc<crate::Error>>>;
+
+/// Whether Windows is set to use light mode for the taskbar, in which case the black logo is shown instead of the white one.
+#[cfg(windows)]
+pub(crate) fn uses_light_theme() -> bool {
+    registry::Hive::CurrentUser.open(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize", registry::Security::QueryValue).ok()
+        .and_then(|key| key.value("SystemUsesLightTheme").ok())
+        .map_or(false, |data| matches!(data, registry::Data::U32(1)))
+}
+
+/// What a tray frontend needs to access and control the rest of the app.
+#[derive(Clone, SmartDefault)]
+pub(crate) struct Context {
+    #[default(broadcast::Sender::new(1))]
+    pub(crate) gui_tx: broadcast::Sender<gui::Message>,
+    pub(crate) http_client: reqwest::Client,
+    pub(crate) config: Config,
+    pub(crate) state: Arc<Mutex<SharedState>>,
+    /// Notified when another instance asks for the tray menu to be shown.
+    pub(crate) show_menu: Arc<Notify>,
+}
+
+impl Context {
+    pub(crate) fn model(&self, state: &SharedState) -> TrayModel {
+        TrayModel::new(&self.config, state)
+    }
+
+    /// Performs an action from the tray menu. For [`Action::Exit`], this closes all launch windows, and the tray frontend is responsible for stopping itself.
+    pub(crate) fn perform(&self, action: &Action, state: SharedState) {
+        match action {
+            Action::OpenWiki(version) => {
+                log::debug!("opening wiki article for version {version} from tray menu");
+                open::that(format!("https://minecraft.wiki/w/Java_Edition_{version}")).expect("failed to open wiki article");
+            }
+            Action::Launch(world) => {
+                log::info!("launching Minecraft into world {world} from tray");
+                self.gui_tx.send(gui::Message::LaunchMinecraft {
+                    config: Some(self.config.clone()),
+                    world: world.clone(),
+                    menu: false,
+                    wait: false,
+                    state,
+                }).allow_unreceived();
+            }
+            Action::OpenProfile(uid) => {
+                log::debug!("opening profile of {uid} from tray menu");
+                open::that(format!("https://wurstmineberg.de/people/{uid}")).expect("failed to open user profile");
+            }
+            Action::SignIn => {
+                log::info!("signing in from tray menu");
+                self.gui_tx.send(gui::Message::SignIn { config: Some(self.config.clone()) }).allow_unreceived();
+            }
+            Action::OpenSettings => {
+                log::debug!("opening config file");
+                if let Err(e) = config::open_file() {
+                    log::error!("failed to open config file: {e} ({e:?})");
+                    crate::dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to open config file"), &format!("{e}\nDebug info: {e:?}"));
+                }
+            }
+            Action::OpenLogFolder => {
+                log::debug!("opening log folder from tray menu");
+                if let Err(e) = logging::open_dir() {
+                    log::error!("failed to open log folder: {e}");
+                    crate::dialog::error(concat!(env!("CARGO_PKG_NAME"), ": failed to open log folder"), &format!("{e}\nDebug info: {e:?}"));
+                }
+            }
+            Action::Exit => {
+                log::info!("exiting from tray menu");
+                self.gui_tx.send(gui::Message::Exit).allow_unreceived();
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! What the tray icon shows, decided independently of the platform's tray API.
+
+use {
+    std::collections::HashMap,
+    itertools::Itertools as _,
+    crate::{
+        MAIN_WORLD,
+        WorldStatus,
+        config::Config,
+        loader::Loader,
+        people::{
+            Person,
+            Uid,
+        },
+    },
+    super::SharedState,
+};
+
+/// What happens when a menu item is selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Opens the Minecraft Wiki article about the given version.
+    OpenWiki(String),
+    /// Opens a launch window for the given world.
+    Launch(String),
+    /// Opens the given person's Wurstmineberg profile.
+    OpenProfile(Uid),
+    SignIn,
+    /// Opens the config file, creating it if necessary.
+    OpenSettings,
+    OpenLogFolder,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MenuItem {
+    Item {
+        label: String,
+        enabled: bool,
+        /// `None` for items that only display information.
+        action: Option<Action>,
+    },
+    Separator,
+}
+
+impl MenuItem {
+    fn action(label: impl Into<String>, action: Action) -> Self {
+        Self::Item { label: label.into(), enabled: true, action: Some(action) }
+    }
+
+    fn label(label: impl Into<String>) -> Self {
+        Self::Item { label: label.into(), enabled: false, action: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TrayModel {
+    /// The tooltip of the tray icon, or `None` if the icon should be hidden.
+    pub(crate) tooltip: Option<String>,
+    /// The action when the tray icon is left-clicked, if any.
+    pub(crate) click_action: Option<Action>,
+    pub(crate) menu: Vec<MenuItem>,
+}
+
+impl TrayModel {
+    pub(crate) fn new(config: &Config, state: &SharedState) -> Self {
+        let mut menu = Vec::default();
+        let tooltip = match *state {
+            Some(Ok((ref people, ref statuses))) => if shows_worlds(config, statuses) {
+                for (world_name, status) in statuses.iter().sorted_by_key(|&(world_name, _)| (world_name != MAIN_WORLD, world_name)) {
+                    if (world_name == MAIN_WORLD && !status.running) || !status.list.is_empty() {
+                        menu.push(MenuItem::label(world_name));
+                        //TODO respect versionLink config
+                        menu.push(if let Some(ref version) = status.version {
+                            MenuItem::action(match status.loader {
+                                Some(loader) if loader != Loader::Vanilla => format!("Version: {version} ({loader})"),
+                                _ => format!("Version: {version}"),
+                            }, Action::OpenWiki(version.clone()))
+                        } else {
+                            MenuItem::label("Unknown Version")
+                        });
+                        if !status.running {
+                            menu.push(MenuItem::label("Server offline"));
+                        }
+                        menu.push(MenuItem::Item {
+                            label: format!("Play on this world"),
+                            enabled: status.running,
+                            action: Some(Action::Launch(world_name.clone())),
+                        });
+                        for uid in &status.list {
+                            menu.push(MenuItem::action(person_name(people, uid), Action::OpenProfile(uid.clone())));
+                        }
+                        menu.push(MenuItem::Separator);
+                    }
+                }
+                Some(if let Ok((world_name, uid)) = statuses.iter().flat_map(|(world_name, world)| world.list.iter().map(move |uid| (world_name, uid))).exactly_one() {
+                    format!("{} is on {world_name}", person_name(people, uid))
+                } else if let Ok((world_name, world)) = statuses.iter().filter(|(_, world)| !world.list.is_empty()).exactly_one() {
+                    format!("{} players are on {world_name}", world.list.len())
+                } else {
+                    format!("{} players are online", statuses.values().map(|world| world.list.len()).sum::<usize>())
+                })
+            } else {
+                None
+            },
+            Some(Err(ref e)) => {
+                menu.push(MenuItem::label(e.to_string()));
+                menu.push(MenuItem::Separator);
+                Some(format!("error getting data"))
+            }
+            None => Some(format!("Wurstmineberg: Loading…")),
+        };
+        menu.push(MenuItem::action("Start Minecraft", Action::Launch(MAIN_WORLD.to_owned())));
+        if config.native_launcher.is_some() {
+            menu.push(MenuItem::action("Sign in to Minecraft…", Action::SignIn));
+        }
+        menu.push(MenuItem::action("Open log folder", Action::OpenLogFolder));
+        menu.push(MenuItem::action("Exit", Action::Exit));
+        Self {
+            click_action: config.left_click_launch.then(|| Action::Launch(MAIN_WORLD.to_owned())),
+            tooltip, menu,
+        }
+    }
+}
+
+/// Whether the worlds should be listed in the tray menu.
+fn shows_worlds(config: &Config, statuses: &HashMap<String, WorldStatus>) -> bool {
+    statuses.values().any(|status| !status.list.is_empty()) || if statuses.get(MAIN_WORLD).is_some_and(|status| status.running) { config.show_if_empty } else { config.show_if_offline }
+}
+
+fn person_name(people: &HashMap<Uid, Person>, uid: &Uid) -> String {
+    people.get(uid).and_then(|person| person.name.clone()).unwrap_or_else(|| uid.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::sync::Arc,
+        super::*,
+    };
+
+    fn world(running: bool, list: &[&str]) -> WorldStatus {
+        WorldStatus {
+            address: None,
+            loader: Some(Loader::Fabric),
+            list: list.iter().map(|&uid| Uid::WmbId(uid.to_owned())).collect(),
+            version: Some(format!("1.21.4")),
+            running,
+        }
+    }
+
+    fn state(worlds: impl IntoIterator<Item = (&'static str, WorldStatus)>) -> SharedState {
+        let people = HashMap::from([(Uid::WmbId(format!("fenhl")), Person { name: Some(format!("Fenhl")) })]);
+        Some(Ok((people, worlds.into_iter().map(|(world_name, status)| (world_name.to_owned(), status)).collect())))
+    }
+
+    /// The menu entries that are shown regardless of the state, with the default config.
+    fn fixed_items() -> Vec<MenuItem> {
+        vec![
+            MenuItem::action("Start Minecraft", Action::Launch(MAIN_WORLD.to_owned())),
+            MenuItem::action("Open log folder", Action::OpenLogFolder),
+            MenuItem::action("Exit", Action::Exit),
+        ]
+    }
+
+    #[test]
+    fn loading() {
+        let model = TrayModel::new(&Config::default(), &None);
+        assert_eq!(model.tooltip.as_deref(), Some("Wurstmineberg: Loading…"));
+        assert_eq!(model.click_action, Some(Action::Launch(MAIN_WORLD.to_owned())));
+        assert_eq!(model.menu, fixed_items());
+    }
+
+    #[test]
+    fn empty() {
+        let state = state([(MAIN_WORLD, world(true, &[]))]);
+        let model = TrayModel::new(&Config::default(), &state);
+        assert_eq!(model.tooltip, None);
+        assert_eq!(model.menu, fixed_items());
+        let config = Config { show_if_empty: true, ..Config::default() };
+        let model = TrayModel::new(&config, &state);
+        assert_eq!(model.tooltip.as_deref(), Some("0 players are online"));
+        // worlds without players are only listed if they're the main world and offline
+        assert_eq!(model.menu, fixed_items());
+    }
+
+    #[test]
+    fn offline() {
+        let state = state([(MAIN_WORLD, world(false, &[]))]);
+        assert_eq!(TrayModel::new(&Config::default(), &state).tooltip, None);
+        let config = Config { show_if_offline: true, ..Config::default() };
+        let model = TrayModel::new(&config, &state);
+        assert_eq!(model.tooltip.as_deref(), Some("0 players are online"));
+        assert_eq!(model.menu[..5], [
+            MenuItem::label(MAIN_WORLD),
+            MenuItem::action("Version: 1.21.4 (Fabric)", Action::OpenWiki(format!("1.21.4"))),
+            MenuItem::label("Server offline"),
+            MenuItem::Item { label: format!("Play on this world"), enabled: false, action: Some(Action::Launch(MAIN_WORLD.to_owned())) },
+            MenuItem::Separator,
+        ]);
+        assert_eq!(model.menu[5..], fixed_items());
+    }
+
+    #[test]
+    fn single_player() {
+        let state = state([(MAIN_WORLD, world(true, &["fenhl"]))]);
+        let model = TrayModel::new(&Config::default(), &state);
+        assert_eq!(model.tooltip.as_deref(), Some("Fenhl is on wurstmineberg"));
+        assert_eq!(model.menu[..5], [
+            MenuItem::label(MAIN_WORLD),
+            MenuItem::action("Version: 1.21.4 (Fabric)", Action::OpenWiki(format!("1.21.4"))),
+            MenuItem::action("Play on this world", Action::Launch(MAIN_WORLD.to_owned())),
+            MenuItem::action("Fenhl", Action::OpenProfile(Uid::WmbId(format!("fenhl")))),
+            MenuItem::Separator,
+        ]);
+        assert_eq!(model.menu[5..], fixed_items());
+    }
+
+    #[test]
+    fn multi_world() {
+        let all_worlds = state([
+            ("creative", world(true, &["fenhl"])),
+            (MAIN_WORLD, world(true, &["alice", "bob"])),
+            ("testing", world(true, &[])),
+        ]);
+        let model = TrayModel::new(&Config::default(), &all_worlds);
+        assert_eq!(model.tooltip.as_deref(), Some("3 players are online"));
+        let world_labels = model.menu.iter().filter_map(|item| match item {
+            MenuItem::Item { label, enabled: false, action: None } => Some(&**label),
+            _ => None,
+        }).collect_vec();
+        // the main world is listed first, worlds without players aren't listed
+        assert_eq!(world_labels, [MAIN_WORLD, "creative"]);
+        // people without a name are shown by their ID
+        assert!(model.menu.contains(&MenuItem::action("alice", Action::OpenProfile(Uid::WmbId(format!("alice"))))));
+        let one_world = state([
+            ("creative", world(true, &[])),
+            (MAIN_WORLD, world(true, &["alice", "bob"])),
+        ]);
+        assert_eq!(TrayModel::new(&Config::default(), &one_world).tooltip.as_deref(), Some("2 players are on wurstmineberg"));
+    }
+
+    #[test]
+    fn error() {
+        let state = Some(Err(Arc::new(crate::Error::UnknownLauncherProfile(format!("Wurstmineberg")))));
+        let model = TrayModel::new(&Config::default(), &state);
+        assert_eq!(model.tooltip.as_deref(), Some("error getting data"));
+        assert_eq!(model.menu[..2], [
+            MenuItem::label("no profile named “Wurstmineberg” in launcher data"),
+            MenuItem::Separator,
+        ]);
+        assert_eq!(model.menu[2..], fixed_items());
+    }
+
+    #[test]
+    fn sign_in_with_native_launcher() {
+        let config = Config { native_launcher: Some(Default::default()), left_click_launch: false, ..Config::default() };
+        let model = TrayModel::new(&config, &None);
+        assert_eq!(model.click_action, None);
+        assert!(model.menu.contains(&MenuItem::action("Sign in to Minecraft…", Action::SignIn)));
+    }
+}
@@ -0,0 +1,164 @@
+//! The tray icon on Windows, using native-windows-gui.
+
+use {
+    std::{
+        cell::RefCell,
+        rc::Rc,
+    },
+    log_lock::*,
+    native_windows_derive as nwd,
+    native_windows_gui::{
+        self as nwg,
+        NativeUi as _,
+    },
+    smart_default::SmartDefault,
+    tokio::runtime::Runtime,
+    crate::jump_list,
+    super::{
+        Action,
+        Context,
+        Error,
+        MenuItem,
+    },
+};
+
+#[derive(SmartDefault, nwd::NwgUi)]
+pub struct SystemTray {
+    #[default(Runtime::new().ok())]
+    runtime: Option<Runtime>,
+    context: Context,
+    #[nwg_control]
+    #[nwg_events(OnInit: [SystemTray::init])]
+    window: nwg::MessageWindow,
+    #[nwg_resource]
+    embed: nwg::EmbedResource,
+    #[nwg_control]
+    #[nwg_events(OnNotice: [SystemTray::set_icon])]
+    update_notice: nwg::Notice,
+    #[nwg_control]
+    #[nwg_events(OnNotice: [SystemTray::show_menu(RC_SELF)])]
+    show_menu_notice: nwg::Notice,
+    event_handler: RefCell<Option<nwg::EventHandler>>,
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_BLACK_16"))]
+    logo_black_16: nwg::Icon,
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_BLACK_32"))]
+    logo_black_32: nwg::Icon,
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_WHITE_16"))]
+    logo_white_16: nwg::Icon,
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("LOGO_WHITE_32"))]
+    logo_white_32: nwg::Icon,
+    #[nwg_control(icon: Some(&data.logo_white_16), tip: Some("Wurstmineberg: Loading…"))]
+    #[nwg_events(MousePressLeftUp: [SystemTray::click], OnContextMenu: [SystemTray::show_menu(RC_SELF)])]
+    tray: nwg::TrayNotification,
+    tray_menu: RefCell<nwg::Menu>,
+    items: RefCell<Vec<(nwg::MenuItem, Option<Action>)>>,
+    separators: RefCell<Vec<nwg::MenuSeparator>>,
+}
+
+impl SystemTray {
+    fn init(&self) {
+        self.set_icon();
+        let update_notifier = self.update_notice.sender();
+        let runtime = self.runtime.as_ref().unwrap();
+        let mut update_jump_list = jump_list::updater(runtime.handle().clone());
+        runtime.spawn(crate::maintain(self.context.http_client.clone(), self.context.state.clone(), move |state| {
+            update_jump_list(state);
+            update_notifier.notice();
+        }));
+        let show_menu_notifier = self.show_menu_notice.sender();
+        let show_menu = self.context.show_menu.clone();
+        runtime.spawn(async move {
+            loop {
+                show_menu.notified().await;
+                show_menu_notifier.notice();
+            }
+        });
+    }
+
+    fn set_icon(&self) {
+        let is_light = super::uses_light_theme();
+        let tooltip = lock!(@blocking lock = self.context.state; self.context.model(&lock).tooltip);
+        self.tray.set_visibility(tooltip.is_some());
+        self.tray.set_icon(match (is_light, nwg::scale_factor() >= 1.5) {
+            (true, true) => &self.logo_black_32,
+            (true, false) => &self.logo_black_16,
+            (false, true) => &self.logo_white_32,
+            (false, false) => &self.logo_white_16,
+        });
+        self.tray.set_tip(&tooltip.unwrap_or_default());
+    }
+
+    fn show_menu(self: &Rc<Self>) {
+        log::debug!("showing tray menu");
+        let (x, y) = nwg::GlobalCursor::position();
+        let mut menu = nwg::Menu::default();
+        nwg::Menu::builder()
+            .popup(true)
+            .parent(&self.window)
+            .build(&mut menu).expect("failed to generate tray menu");
+        let app = self.clone();
+        if let Some(previous_event_handler) = self.event_handler.replace(Some(nwg::full_bind_event_handler(&self.window.handle, move |event, _, handle| if let nwg::Event::OnMenuItemSelected = event {
+            let action = app.items.borrow().iter().find(|(item, _)| item.handle == handle).and_then(|(_, action)| action.clone());
+            if let Some(action) = action {
+                app.perform(&action);
+            }
+        }))) {
+            nwg::unbind_event_handler(&previous_event_handler);
+        }
+        let model = lock!(@blocking lock = self.context.state; self.context.model(&lock));
+        let mut items = Vec::default();
+        let mut separators = Vec::default();
+        for item in model.menu {
+            match item {
+                MenuItem::Item { label, enabled, action } => {
+                    let mut item = nwg::MenuItem::default();
+                    nwg::MenuItem::builder()
+                        .text(&label)
+                        .disabled(!enabled)
+                        .parent(&menu)
+                        .build(&mut item).expect("failed to generate tray menu");
+                    items.push((item, action));
+                }
+                MenuItem::Separator => {
+                    let mut sep = nwg::MenuSeparator::default();
+                    nwg::MenuSeparator::builder()
+                        .parent(&menu)
+                        .build(&mut sep).expect("failed to generate tray menu");
+                    separators.push(sep);
+                }
+            }
+        }
+        *self.items.borrow_mut() = items;
+        *self.separators.borrow_mut() = separators;
+        menu.popup(x, y);
+        *self.tray_menu.borrow_mut() = menu;
+    }
+
+    fn click(&self) {
+        let action = lock!(@blocking lock = self.context.state; self.context.model(&lock).click_action);
+        if let Some(action) = action {
+            self.perform(&action);
+        }
+    }
+
+    fn perform(&self, action: &Action) {
+        let state = lock!(@blocking lock = self.context.state; lock.clone());
+        self.context.perform(action, state);
+        if let Action::Exit = action {
+            nwg::stop_thread_dispatch();
+        }
+    }
+}
+
+/// Shows the tray icon until the user exits using the tray menu.
+pub(crate) fn run(runtime: Runtime, context: Context) -> Result<(), Error> {
+    nwg::init()?;
+    let app = SystemTray::build_ui(SystemTray {
+        runtime: Some(runtime),
+        context,
+        ..SystemTray::default()
+    })?;
+    nwg::dispatch_thread_events();
+    drop(app);
+    Ok(())
+}
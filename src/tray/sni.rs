@@ -0,0 +1,175 @@
+//! The tray icon on Linux and other platforms with a StatusNotifierItem host, using D-Bus via ksni.
+
+use {
+    image::ImageFormat,
+    ksni::{
+        Icon,
+        Status,
+        ToolTip,
+        TrayMethods as _,
+        menu::StandardItem,
+    },
+    tokio::{
+        runtime::Runtime,
+        sync::mpsc,
+    },
+    wheel::traits::SendResultExt as _,
+    super::{
+        Action,
+        Context,
+        Error,
+        MenuItem,
+        SharedState,
+    },
+};
+
+struct Tray {
+    context: Context,
+    /// A copy of the shared state, since the menu is built synchronously from within the async runtime.
+    state: SharedState,
+    icons: Vec<Icon>,
+}
+
+impl Tray {
+    fn perform(&self, action: &Action) {
+        self.context.perform(action, self.state.clone());
+        // on exit, the process exits once the launch window event loop does
+    }
+}
+
+impl ksni::Tray for Tray {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").to_owned()
+    }
+
+    fn title(&self) -> String {
+        format!("Wurstmineberg")
+    }
+
+    fn status(&self) -> Status {
+        // hosts usually hide passive items, which corresponds to hiding the icon on Windows
+        if self.context.model(&self.state).tooltip.is_some() { Status::Active } else { Status::Passive }
+    }
+
+    fn icon_pixmap(&self) -> Vec<Icon> {
+        self.icons.clone()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: self.context.model(&self.state).tooltip.unwrap_or_default(),
+            ..ToolTip::default()
+        }
+    }
+
+    fn activate(&mut self, _: i32, _: i32) {
+        if let Some(action) = self.context.model(&self.state).click_action {
+            self.perform(&action);
+        }
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        log::debug!("showing tray menu");
+        self.context.model(&self.state).menu.into_iter().map(|item| match item {
+            MenuItem::Item { label, enabled, action } => StandardItem {
+                activate: Box::new(move |tray: &mut Self| if let Some(ref action) = action {
+                    tray.perform(action);
+                }),
+                label, enabled,
+                ..StandardItem::default()
+            }.into(),
+            MenuItem::Separator => ksni::MenuItem::Separator,
+        }).collect()
+    }
+}
+
+/// Converts an icon file to the ARGB32 format used by StatusNotifierItem.
+fn icon(data: &[u8]) -> Result<Icon, Error> {
+    let image = image::load_from_memory_with_format(data, ImageFormat::Ico)?.to_rgba8();
+    Ok(Icon {
+        width: image.width().try_into().expect("icon too large"),
+        height: image.height().try_into().expect("icon too large"),
+        data: image.pixels().flat_map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            [a, r, g, b]
+        }).collect(),
+    })
+}
+
+/// Shows the tray icon until the D-Bus connection is closed. Exiting via the tray menu is handled by the launch window event loop.
+pub(crate) fn run(runtime: Runtime, context: Context) -> Result<(), Error> {
+    runtime.block_on(async move {
+        // most panels on Linux are dark, and the system theme isn't easily accessible, so the white icons are used
+        let icons = vec![
+            icon(include_bytes!("../../assets/logo-white-16.ico"))?,
+            icon(include_bytes!("../../assets/logo-white-32.ico"))?,
+        ];
+        let handle = Tray { context: context.clone(), state: None, icons }.spawn().await?;
+        let show_menu = context.show_menu.clone();
+        tokio::spawn(async move {
+            loop {
+                show_menu.notified().await;
+                // the StatusNotifierItem spec has no way to ask the host to open the menu
+                log::info!("another instance asked to show the tray menu, which isn't supported by StatusNotifierItem hosts");
+            }
+        });
+        let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+        tokio::spawn(crate::maintain(context.http_client.clone(), context.state.clone(), move |state| update_tx.send(state.clone()).allow_unreceived()));
+        while let Some(state) = update_rx.recv().await {
+            if handle.update(|tray| tray.state = state).await.is_none() { break }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::collections::HashMap,
+        crate::{
+            MAIN_WORLD,
+            WorldStatus,
+            people::{
+                Person,
+                Uid,
+            },
+            test_util,
+        },
+        super::*,
+    };
+
+    fn world(running: bool, list: Vec<Uid>) -> WorldStatus {
+        WorldStatus { address: None, loader: None, list, running, version: Some(format!("1.21.4")) }
+    }
+
+    #[test]
+    fn icons_are_argb() {
+        let icon = icon(include_bytes!("../../assets/logo-white-16.ico")).unwrap();
+        assert!(icon.width > 0 && icon.height > 0);
+        assert_eq!(icon.data.len(), usize::try_from(icon.width * icon.height * 4).unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a D-Bus session bus"]
+    async fn updates_on_session_bus() {
+        let context = Context {
+            http_client: test_util::http_client(),
+            ..Context::default()
+        };
+        let icons = vec![icon(include_bytes!("../../assets/logo-white-16.ico")).unwrap()];
+        // there may not be a StatusNotifierWatcher in the test environment, but the item is still exported on the bus
+        let handle = Tray { context, state: None, icons }.assume_sni_available(true).spawn().await.unwrap();
+        assert_eq!(handle.update(|tray| tray.status()).await, Some(Status::Active));
+        let empty = Some(Ok((HashMap::default(), HashMap::from([(MAIN_WORLD.to_owned(), world(true, Vec::default()))]))));
+        assert_eq!(handle.update(|tray| { tray.state = empty; tray.status() }).await, Some(Status::Passive));
+        let uid = Uid::WmbId(format!("fenhl"));
+        let online = Some(Ok((
+            HashMap::from([(uid.clone(), Person { name: Some(format!("Fenhl")) })]),
+            HashMap::from([(MAIN_WORLD.to_owned(), world(true, vec![uid]))]),
+        )));
+        let (status, tool_tip) = handle.update(|tray| { tray.state = online; (tray.status(), tray.tool_tip().title) }).await.unwrap();
+        assert_eq!(status, Status::Active);
+        assert_eq!(tool_tip, "Fenhl is on wurstmineberg");
+        handle.shutdown().await;
+    }
+}
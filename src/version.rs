@@ -0,0 +1,47 @@
+//! Parsing Minecraft version names to decide which features and Java versions they need.
+
+/// A Minecraft version, as far as it's needed to compare it against the versions where launch requirements changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Version {
+    /// A release such as `1.20.4` or `26.1`, or a pre-release or release candidate of one, such as `1.20-pre1`.
+    Release {
+        major: u32,
+        minor: u32,
+        patch: u32,
+    },
+    /// A weekly snapshot such as `23w14a`.
+    Snapshot {
+        year: u32,
+        week: u32,
+    },
+}
+
+impl Version {
+    /// Returns `None` for version names in other formats, e.g. April Fools' Day versions or versions from before the release of 1.0.
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        if let Some((year, rest)) = version.split_once('w') && year.len() == 2 && rest.len() > 2 && rest.is_char_boundary(2) {
+            let (week, suffix) = rest.split_at(2);
+            if suffix.chars().all(|c| c.is_ascii_lowercase()) {
+                return Some(Self::Snapshot {
+                    year: year.parse().ok()?,
+                    week: week.parse().ok()?,
+                })
+            }
+        }
+        let release = version.split_once('-').map_or(version, |(release, _)| release);
+        let mut parts = release.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = parts.next().map_or(Some(0), |patch| patch.parse().ok())?;
+        if parts.next().is_some() { return None }
+        Some(Self::Release { major, minor, patch })
+    }
+
+    /// Whether this is the given release or a later version, including the given release's pre-releases as well as snapshots starting with `first_snapshot`.
+    pub(crate) fn is_at_least(&self, release: (u32, u32, u32), first_snapshot: (u32, u32)) -> bool {
+        match *self {
+            Self::Release { major, minor, patch } => (major, minor, patch) >= release,
+            Self::Snapshot { year, week } => (year, week) >= first_snapshot,
+        }
+    }
+}